@@ -0,0 +1,146 @@
+use std::sync::Arc;
+use std::time::Instant;
+
+use time::OffsetDateTime;
+use tracing::{error, info, info_span, Instrument};
+
+use crate::check::{DynCheck, Report};
+use crate::juggler::Event;
+use crate::logging;
+use crate::meta::{Heartbeat, SelfMonitoring};
+use crate::silence::{self, Silences};
+use crate::sink::Sink;
+use crate::status::StatusBoard;
+use crate::BoxError;
+
+/// Runs every registered check and hands the events to every sink.
+pub struct Agent {
+    checks: Vec<Box<dyn DynCheck>>,
+    sinks: Vec<Box<dyn Sink>>,
+    status: Option<Arc<StatusBoard>>,
+    silences: Silences,
+    meta: Option<SelfMonitoring>,
+    heartbeat: Option<Heartbeat>,
+}
+
+impl Agent {
+    pub fn new(checks: Vec<Box<dyn DynCheck>>, sinks: Vec<Box<dyn Sink>>) -> Self {
+        Agent {
+            checks,
+            sinks,
+            status: None,
+            silences: Silences::default(),
+            meta: None,
+            heartbeat: None,
+        }
+    }
+
+    /// Keeps the latest result of every run on the board.
+    pub fn with_status(mut self, board: Arc<StatusBoard>) -> Self {
+        self.status = Some(board);
+        self
+    }
+
+    pub fn with_silences(mut self, silences: Silences) -> Self {
+        self.silences = silences;
+        self
+    }
+
+    /// Adds a `monitoring-self` event about the run itself.
+    pub fn with_self_monitoring(mut self, meta: SelfMonitoring) -> Self {
+        self.meta = Some(meta);
+        self
+    }
+
+    /// Pings the heartbeat after every run whose events were all sent
+    /// and that collected at least one target.
+    pub fn with_heartbeat(mut self, heartbeat: Heartbeat) -> Self {
+        self.heartbeat = Some(heartbeat);
+        self
+    }
+
+    pub async fn run_once(&self) -> Result<Vec<Report>, BoxError> {
+        let started = Instant::now();
+        let reports =
+            futures::future::join_all(self.checks.iter().map(|check| check.run())).await;
+        let duration = started.elapsed();
+
+        // a broken silence file must not hide the events
+        let active = self
+            .silences
+            .active(OffsetDateTime::now_utc())
+            .unwrap_or_else(|e| {
+                error!("failed to load silences: {e}");
+                vec![]
+            });
+
+        if let Some(board) = &self.status {
+            board.update(&reports, &active);
+        }
+
+        let mut events: Vec<Event> = reports
+            .iter()
+            .flat_map(|report| report.events.iter().cloned())
+            .collect();
+        // events of failed targets alone do not make a run
+        let collected = reports
+            .iter()
+            .any(|report| report.targets.iter().any(|t| t.error.is_none()));
+
+        // sent even when nothing was collected, that is when it matters most
+        if let Some(meta) = &self.meta {
+            events.push(meta.event(&reports, duration));
+        }
+
+        if events.is_empty() {
+            return Err("no events to send".into());
+        }
+
+        let recorded = silence::mark(events.clone(), &active);
+        let events = silence::apply(events, &active);
+
+        let mut failed = Vec::new();
+        for sink in &self.sinks {
+            let events = if sink.records() { &recorded } else { &events };
+            if events.is_empty() {
+                continue;
+            }
+
+            let span = info_span!("send", sink = sink.name(), events = events.len());
+            let started = Instant::now();
+            let sent = sink.send(events).instrument(span.clone()).await;
+            let send_ms = logging::millis(started.elapsed());
+
+            let _entered = span.enter();
+            match sent {
+                Ok(()) => info!(send_ms, "sent"),
+                Err(e) => {
+                    error!(send_ms, error = %e, "failed to send events to {}", sink.name());
+                    failed.push(sink.name());
+                }
+            }
+        }
+
+        if !failed.is_empty() {
+            return Err(format!("failed to send events to {}", failed.join(", ")).into());
+        }
+
+        // the events are out, but a heartbeat would tell that monitoring works
+        // while no target could be collected
+        if !collected {
+            return Err("no target could be collected".into());
+        }
+
+        self.beat().await;
+        Ok(reports)
+    }
+
+    // the run itself succeeded, a missed ping is for the receiving side to notice
+    async fn beat(&self) {
+        if let Some(heartbeat) = &self.heartbeat {
+            if let Err(e) = heartbeat.beat().await {
+                error!("failed to send heartbeat: {e}");
+            }
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Serialize;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::juggler::Event;
+use crate::sink::{Change, Firing, Sink};
+use crate::state::State;
+use crate::BoxError;
+
+/// One alert of `POST /api/v2/alerts`.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Alert {
+    pub labels: BTreeMap<String, String>,
+    pub annotations: BTreeMap<String, String>,
+    pub starts_at: String,
+    pub ends_at: String,
+}
+
+fn rfc3339(at: OffsetDateTime) -> String {
+    at.format(&Rfc3339).unwrap()
+}
+
+/// Alertmanager groups alerts by their labels, so an alert is identified by
+/// source, host, service, instance and severity. A resolved alert ends now,
+/// a firing one is re-sent every run and ends after `ttl` unless refreshed.
+pub fn alertify(source: &str, change: &Change, now: OffsetDateTime, ttl: Duration) -> Alert {
+    let (event, status, ends_at) = match change {
+        Change::Trigger(event) => (*event, event.status, now + ttl),
+        Change::Resolve { event, previous } => (*event, *previous, now),
+    };
+
+    let mut labels = BTreeMap::from([
+        (String::from("alertname"), event.service.clone()),
+        (String::from("host"), event.host.clone()),
+        (String::from("source"), source.to_string()),
+        (String::from("severity"), status.severity().to_string()),
+    ]);
+    if !event.instance.is_empty() {
+        labels.insert(String::from("instance"), event.instance.clone());
+    }
+
+    let annotations = BTreeMap::from([
+        (
+            String::from("summary"),
+            format!("{} on {} is {:?}", event.service, event.host, event.status),
+        ),
+        (String::from("description"), event.description.clone()),
+        (String::from("tags"), event.tags.join(",")),
+    ]);
+
+    Alert {
+        labels,
+        annotations,
+        starts_at: rfc3339(now),
+        ends_at: rfc3339(ends_at),
+    }
+}
+
+/// Alertmanager v2 API client.
+pub struct Alertmanager {
+    client: Client,
+    url: String,
+    source: String,
+    ttl: Duration,
+    firing: Firing,
+}
+
+impl Alertmanager {
+    pub fn new(client: Client, url: String, source: String, ttl: Duration) -> Self {
+        Alertmanager {
+            client,
+            url,
+            source,
+            ttl,
+            firing: Firing::new("alertmanager/firing", Arc::new(State::memory())),
+        }
+    }
+
+    /// Remembers the open alerts in the agent state, so they are resolved after a restart.
+    pub fn with_state(mut self, state: Arc<State>) -> Self {
+        self.firing = Firing::new("alertmanager/firing", state);
+        self
+    }
+}
+
+#[async_trait]
+impl Sink for Alertmanager {
+    fn name(&self) -> &str {
+        "alertmanager"
+    }
+
+    async fn send(&self, events: &[Event]) -> Result<(), BoxError> {
+        let now = OffsetDateTime::now_utc().replace_nanosecond(0).unwrap();
+        let (changes, firing) = self.firing.changes(events);
+        let alerts: Vec<Alert> = changes
+            .iter()
+            .map(|change| alertify(&self.source, change, now, self.ttl))
+            .collect();
+
+        if alerts.is_empty() {
+            return Ok(());
+        }
+
+        self.client
+            .post(format!("{}/api/v2/alerts", self.url))
+            .json(&alerts)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        self.firing.commit(firing);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::juggler::Status;
+
+    fn event(status: Status) -> Event {
+        Event {
+            host: String::from("zoo1-test"),
+            service: String::from("state"),
+            instance: String::from(""),
+            status,
+            description: String::from("leader. followers: 1/2"),
+            tags: vec![String::from("zoo"), String::from("k8s")],
+            metrics: Default::default(),
+            cluster_level: false,
+        }
+    }
+
+    #[test]
+    fn firing_alert_should_end_after_ttl() {
+        let event = event(Status::WARN);
+        let alert = alertify(
+            "agent",
+            &Change::Trigger(&event),
+            OffsetDateTime::UNIX_EPOCH,
+            Duration::from_secs(300),
+        );
+
+        assert_eq!(alert.labels["alertname"], "state");
+        assert_eq!(alert.labels["severity"], "warning");
+        assert!(!alert.labels.contains_key("instance"));
+        assert_eq!(alert.annotations["summary"], "state on zoo1-test is WARN");
+        assert_eq!(alert.annotations["tags"], "zoo,k8s");
+        assert_eq!(alert.starts_at, "1970-01-01T00:00:00Z");
+        assert_eq!(alert.ends_at, "1970-01-01T00:05:00Z");
+    }
+
+    #[test]
+    fn resolved_alert_should_keep_previous_labels() {
+        let event = event(Status::OK);
+        let alert = alertify(
+            "agent",
+            &Change::Resolve {
+                event: &event,
+                previous: Status::CRIT,
+            },
+            OffsetDateTime::UNIX_EPOCH,
+            Duration::from_secs(300),
+        );
+
+        assert_eq!(alert.labels["severity"], "critical");
+        assert_eq!(alert.ends_at, "1970-01-01T00:00:00Z");
+    }
+}
@@ -0,0 +1,172 @@
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use tracing::{info, info_span, warn, Instrument};
+
+use crate::juggler::Event;
+use crate::logging;
+use crate::BoxError;
+
+/// A check of one cluster: collect a sample from every target,
+/// then evaluate all samples together into events.
+#[async_trait]
+pub trait Check: Send + Sync {
+    type Sample: Send;
+
+    /// Kind of the check, e.g. `zoo`.
+    fn kind(&self) -> &'static str;
+
+    fn cluster(&self) -> &str;
+
+    /// Targets of this run, looked up again on every run when discovered.
+    async fn targets(&self) -> Vec<String>;
+
+    async fn collect(&self, target: &str) -> Result<Self::Sample, BoxError>;
+
+    fn evaluate(&self, samples: Vec<(String, Self::Sample)>) -> Vec<Event>;
+
+    /// Whether to collect a target again within the run after its `attempt` failed.
+    fn retry(&self, _error: &BoxError, _attempt: u32) -> bool {
+        false
+    }
+
+    /// Event for a target that could not be collected. It is sent even when
+    /// no target of the cluster was collected, `evaluate` is skipped then.
+    fn failed(&self, _error: &BoxError) -> Option<Event> {
+        None
+    }
+}
+
+const RETRY_DELAY: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone)]
+pub struct TargetResult {
+    pub target: String,
+    /// Juggler host of the target's events.
+    pub host: String,
+    pub latency: Duration,
+    pub error: Option<String>,
+}
+
+/// Outcome of one run of a check.
+#[derive(Debug)]
+pub struct Report {
+    pub kind: &'static str,
+    pub cluster: String,
+    pub targets: Vec<TargetResult>,
+    pub events: Vec<Event>,
+}
+
+/// One attempt at collecting a target, logged with the time spent in each phase.
+async fn collect_once<T: Check>(
+    check: &T,
+    target: &str,
+) -> (Duration, Result<T::Sample, BoxError>) {
+    let started = Instant::now();
+    let (result, phases) = logging::phases(check.collect(target)).await;
+    let latency = started.elapsed();
+
+    let latency_ms = logging::millis(latency);
+    let connect_ms = phases.connect.map(logging::millis);
+    let read_ms = phases.read.map(logging::millis);
+    let parse_ms = phases.parse.map(logging::millis);
+    match &result {
+        Ok(_) => info!(latency_ms, connect_ms, read_ms, parse_ms, "collected"),
+        Err(e) => warn!(
+            latency_ms,
+            connect_ms,
+            read_ms,
+            parse_ms,
+            error = %e,
+            "failed to collect"
+        ),
+    }
+
+    (latency, result)
+}
+
+/// Object safe side of `Check`, so checks of different kinds can be registered together.
+#[async_trait]
+pub trait DynCheck: Send + Sync {
+    fn kind(&self) -> &'static str;
+
+    fn cluster(&self) -> &str;
+
+    async fn run(&self) -> Report;
+}
+
+#[async_trait]
+impl<T: Check> DynCheck for T {
+    fn kind(&self) -> &'static str {
+        Check::kind(self)
+    }
+
+    fn cluster(&self) -> &str {
+        Check::cluster(self)
+    }
+
+    async fn run(&self) -> Report {
+        let targets = self.targets().await;
+        let results = futures::future::join_all(targets.iter().map(|target| async move {
+            let mut attempt = 1;
+            loop {
+                let span = info_span!(
+                    "collect",
+                    host = %target,
+                    cluster = Check::cluster(self),
+                    check = Check::kind(self),
+                    attempt,
+                );
+                let (latency, result) = collect_once(self, target).instrument(span).await;
+
+                match &result {
+                    Err(e) if self.retry(e, attempt) => {
+                        tokio::time::sleep(RETRY_DELAY).await;
+                        attempt += 1;
+                    }
+                    _ => return (target.clone(), latency, result),
+                }
+            }
+        }))
+        .await;
+
+        let mut samples = Vec::new();
+        let mut failures = Vec::new();
+        let mut targets = Vec::new();
+
+        for (target, latency, result) in results {
+            let error = match result {
+                Ok(sample) => {
+                    samples.push((target.clone(), sample));
+                    None
+                }
+                Err(e) => {
+                    let error = e.to_string();
+                    failures.extend(self.failed(&e));
+                    Some(error)
+                }
+            };
+
+            targets.push(TargetResult {
+                host: target.clone(),
+                target,
+                latency,
+                error,
+            });
+        }
+
+        let mut events = if samples.is_empty() {
+            vec![]
+        } else {
+            self.evaluate(samples)
+        };
+        events.extend(failures);
+
+        Report {
+            kind: Check::kind(self),
+            cluster: Check::cluster(self).to_string(),
+            targets,
+            events,
+        }
+    }
+}
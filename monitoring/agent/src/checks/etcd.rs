@@ -0,0 +1,263 @@
+use std::collections::BTreeSet;
+
+use async_trait::async_trait;
+use reqwest::{Client, ClientBuilder};
+use serde::{Deserialize, Deserializer};
+
+use crate::check::Check;
+use crate::checks::address;
+use crate::config::EtcdConfig;
+use crate::discovery::Hosts;
+use crate::juggler::{metrics, Event, Status};
+use crate::logging::{timed, timed_sync, Phase};
+use crate::BoxError;
+
+const ETCD_PORT: u16 = 2379;
+const TIMEOUT: u64 = 3;
+
+// the JSON gateway encodes uint64 as strings
+fn number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Number {
+        String(String),
+        Number(u64),
+    }
+
+    match Number::deserialize(deserializer)? {
+        Number::String(s) => s.parse().map_err(serde::de::Error::custom),
+        Number::Number(n) => Ok(n),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Health {
+    pub health: String,
+    #[serde(default)]
+    pub reason: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Header {
+    #[serde(deserialize_with = "number")]
+    pub member_id: u64,
+}
+
+/// `/v3/maintenance/status` reply.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceStatus {
+    pub header: Header,
+    pub version: String,
+    #[serde(deserialize_with = "number")]
+    pub db_size: u64,
+    /// Left out by the JSON gateway when it is zero, i.e. there is no leader.
+    #[serde(default, deserialize_with = "number")]
+    pub leader: u64,
+    #[serde(default, deserialize_with = "number")]
+    pub raft_term: u64,
+    #[serde(default)]
+    pub errors: Vec<String>,
+}
+
+/// What a single member reported.
+#[derive(Debug)]
+pub struct EtcdSample {
+    pub health: Health,
+    pub status: MaintenanceStatus,
+}
+
+pub struct EtcdCheck {
+    cluster: String,
+    hosts: Hosts,
+    scheme: String,
+    quota_bytes: u64,
+    db_warn: f64,
+    client: Client,
+}
+
+impl EtcdCheck {
+    pub fn new(config: &EtcdConfig) -> Result<Self, BoxError> {
+        let label = format!("etcd/{}", config.cluster);
+
+        Ok(EtcdCheck {
+            cluster: config.cluster.clone(),
+            hosts: Hosts::new(label, config.hosts.clone(), config.discovery.as_ref())?,
+            scheme: config.scheme.clone(),
+            quota_bytes: config.quota_bytes,
+            db_warn: config.db_warn,
+            client: ClientBuilder::new()
+                .timeout(tokio::time::Duration::from_secs(TIMEOUT))
+                .build()?,
+        })
+    }
+
+    fn event(
+        &self,
+        host: &str,
+        service: &str,
+        status: Status,
+        description: String,
+        values: &[(&str, f64)],
+    ) -> Event {
+        Event {
+            host: host.to_string(),
+            service: service.to_string(),
+            instance: String::from(""),
+            status,
+            description,
+            tags: vec![String::from("etcd"), String::from("monitoring")],
+            metrics: metrics(values),
+            cluster_level: false,
+        }
+    }
+
+    fn state(&self, host: &str, sample: &EtcdSample, samples: &[(String, EtcdSample)]) -> Event {
+        let leaders: BTreeSet<u64> = samples.iter().map(|(_, s)| s.status.leader).collect();
+        let terms: BTreeSet<u64> = samples.iter().map(|(_, s)| s.status.raft_term).collect();
+
+        let status = &sample.status;
+        let is_leader = status.leader == status.header.member_id;
+
+        let mut description = if is_leader {
+            format!("leader. members: {}/{}", samples.len(), self.hosts.count())
+        } else {
+            String::from("follower")
+        };
+        description = format!("{description}, term: {}", status.raft_term);
+
+        let mut problems = Vec::new();
+        if sample.health.health != "true" {
+            problems.push(format!("unhealthy: {}", sample.health.reason));
+        }
+        if status.leader == 0 {
+            problems.push(String::from("no leader"));
+        }
+        if leaders.len() > 1 {
+            problems.push(format!("members disagree on the leader: {leaders:x?}"));
+        }
+        if terms.len() > 1 {
+            problems.push(format!("members disagree on the raft term: {terms:?}"));
+        }
+        problems.extend(status.errors.iter().cloned());
+
+        let status = if !problems.is_empty() {
+            description = format!("{description}\n{}", problems.join("\n"));
+            Status::CRIT
+        } else if is_leader && samples.len() < self.hosts.count() {
+            Status::WARN
+        } else {
+            Status::OK
+        };
+
+        let values = [
+            ("leader", if is_leader { 1.0 } else { 0.0 }),
+            ("raft_term", sample.status.raft_term as f64),
+            ("members", samples.len() as f64),
+        ];
+
+        self.event(host, "etcd-state", status, description, &values)
+    }
+
+    fn db(&self, host: &str, sample: &EtcdSample) -> Event {
+        let size = sample.status.db_size;
+        let ratio = size as f64 / self.quota_bytes as f64;
+
+        let status = if size >= self.quota_bytes {
+            Status::CRIT
+        } else if ratio >= self.db_warn {
+            Status::WARN
+        } else {
+            Status::OK
+        };
+
+        let description = format!(
+            "db size: {size}/{} bytes ({:.0}%)",
+            self.quota_bytes,
+            ratio * 100.0
+        );
+
+        let values = [
+            ("db_size", size as f64),
+            ("quota_bytes", self.quota_bytes as f64),
+        ];
+
+        self.event(host, "etcd-db", status, description, &values)
+    }
+}
+
+#[async_trait]
+impl Check for EtcdCheck {
+    type Sample = EtcdSample;
+
+    fn kind(&self) -> &'static str {
+        "etcd"
+    }
+
+    fn cluster(&self) -> &str {
+        &self.cluster
+    }
+
+    async fn targets(&self) -> Vec<String> {
+        self.hosts.refresh().await
+    }
+
+    async fn collect(&self, target: &str) -> Result<EtcdSample, BoxError> {
+        let base = format!("{}://{}", self.scheme, address(target, ETCD_PORT));
+
+        // an unhealthy member answers 503 with the same body
+        let request = self.client.get(format!("{base}/health")).send();
+        let response = timed(Phase::Connect, request).await?;
+        let health = timed(Phase::Read, response.bytes()).await?;
+
+        let request = self
+            .client
+            .post(format!("{base}/v3/maintenance/status"))
+            .body("{}")
+            .send();
+        let response = timed(Phase::Connect, request).await?.error_for_status()?;
+        let status = timed(Phase::Read, response.bytes()).await?;
+
+        timed_sync(Phase::Parse, || {
+            Ok(EtcdSample {
+                health: serde_json::from_slice(&health)?,
+                status: serde_json::from_slice(&status)?,
+            })
+        })
+    }
+
+    fn evaluate(&self, samples: Vec<(String, EtcdSample)>) -> Vec<Event> {
+        samples
+            .iter()
+            .flat_map(|(host, sample)| [self.state(host, sample, &samples), self.db(host, sample)])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_should_parse_string_numbers() {
+        let status: MaintenanceStatus = serde_json::from_str(
+            r#"{
+                "header": {"cluster_id": "14841639068965178418", "member_id": "10276657743932975437", "revision": "1", "raft_term": "2"},
+                "version": "3.5.9",
+                "dbSize": "20480",
+                "leader": "10276657743932975437",
+                "raftIndex": "4",
+                "raftTerm": "2",
+                "raftAppliedIndex": "4",
+                "dbSizeInUse": "16384"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(status.header.member_id, 10276657743932975437);
+        assert_eq!(status.leader, status.header.member_id);
+        assert_eq!(status.db_size, 20480);
+        assert_eq!(status.raft_term, 2);
+        assert!(status.errors.is_empty());
+    }
+}
@@ -0,0 +1,185 @@
+pub mod etcd;
+pub mod postgres;
+pub mod probe;
+pub mod redis;
+pub mod rmq;
+pub mod zoo;
+pub mod zoo_clients;
+
+use std::sync::Arc;
+
+use crate::check::DynCheck;
+use crate::config::Config;
+use crate::enrich::{Enriched, Enricher, Enrichment};
+use crate::naming::{self, HostNaming, Named, Namer};
+use crate::state::State;
+use crate::BoxError;
+
+/// Splits a host into its name and port, `default_port` when it comes without one.
+/// Takes `host`, `host:port`, a bare IPv6 address, `[IPv6]` and `[IPv6]:port`,
+/// e.g. the AAAA records of a discovery without a port.
+pub fn host_port(target: &str, default_port: u16) -> Result<(&str, u16), BoxError> {
+    if let Some(bracketed) = target.strip_prefix('[') {
+        return match bracketed.split_once(']') {
+            Some((host, "")) => Ok((host, default_port)),
+            Some((host, port)) => match port.strip_prefix(':') {
+                Some(port) => Ok((host, port.parse()?)),
+                None => Err(format!("invalid host: {target}").into()),
+            },
+            None => Err(format!("invalid host: {target}").into()),
+        };
+    }
+
+    // a bare IPv6 address has more than one colon
+    match target.split_once(':') {
+        Some((host, port)) if !port.contains(':') => Ok((host, port.parse()?)),
+        _ => Ok((target, default_port)),
+    }
+}
+
+/// `host:port` to connect to, with IPv6 addresses in brackets.
+/// A host that does not parse is left for the connection to fail on.
+pub fn address(target: &str, default_port: u16) -> String {
+    match host_port(target, default_port) {
+        Ok((host, port)) if host.contains(':') => format!("[{host}]:{port}"),
+        Ok((host, port)) => format!("{host}:{port}"),
+        Err(_) => target.to_string(),
+    }
+}
+
+// a check with its cluster's own naming and enrichment
+type Registered<'a> = (
+    Box<dyn DynCheck>,
+    Option<&'a HostNaming>,
+    Option<&'a Enrichment>,
+);
+
+/// Builds a check for every cluster in the config,
+/// each one renaming its event hosts by the cluster's naming strategy
+/// and enriching its events when the cluster or its kind has an enrichment.
+/// Checks that compare runs keep what they have seen in `state`.
+pub fn registry(config: &Config, state: &Arc<State>) -> Result<Vec<Box<dyn DynCheck>>, BoxError> {
+    let mut checks: Vec<Registered> = Vec::new();
+
+    for cluster in &config.zoo {
+        checks.push((
+            Box::new(zoo::ZooCheck::new(cluster)?.with_state(state.clone())),
+            cluster.naming.as_ref(),
+            cluster.enrichment.as_ref(),
+        ));
+
+        if let Some(clients) = &cluster.clients {
+            checks.push((
+                Box::new(zoo_clients::ZooClientsCheck::new(cluster, clients)?),
+                cluster.naming.as_ref(),
+                cluster.enrichment.as_ref(),
+            ));
+        }
+    }
+
+    for cluster in &config.rmq {
+        checks.push((
+            Box::new(rmq::RmqCheck::new(cluster)?),
+            cluster.naming.as_ref(),
+            cluster.enrichment.as_ref(),
+        ));
+    }
+
+    for cluster in &config.redis {
+        checks.push((
+            Box::new(redis::RedisCheck::new(cluster)?.with_state(state.clone())),
+            cluster.naming.as_ref(),
+            cluster.enrichment.as_ref(),
+        ));
+    }
+
+    for cluster in &config.etcd {
+        checks.push((
+            Box::new(etcd::EtcdCheck::new(cluster)?),
+            cluster.naming.as_ref(),
+            cluster.enrichment.as_ref(),
+        ));
+    }
+
+    for cluster in &config.postgres {
+        checks.push((
+            Box::new(postgres::PostgresCheck::new(cluster)?),
+            cluster.naming.as_ref(),
+            cluster.enrichment.as_ref(),
+        ));
+    }
+
+    for probe in &config.tcp {
+        checks.push((
+            Box::new(probe::TcpProbe::new(probe)),
+            probe.naming.as_ref(),
+            probe.enrichment.as_ref(),
+        ));
+    }
+
+    for probe in &config.http {
+        checks.push((
+            Box::new(probe::HttpProbe::new(probe)?),
+            probe.naming.as_ref(),
+            probe.enrichment.as_ref(),
+        ));
+    }
+
+    let namings: Vec<&HostNaming> = checks
+        .iter()
+        .map(|(_, naming, _)| naming.unwrap_or(&config.naming))
+        .collect();
+    let resolver = if namings
+        .iter()
+        .any(|naming| matches!(naming, HostNaming::ReverseDns { .. }))
+    {
+        Some(naming::resolver()?)
+    } else {
+        None
+    };
+
+    Ok(checks
+        .into_iter()
+        .zip(namings)
+        .map(|((check, _, enrichment), naming)| {
+            let namer = Namer::new(naming.clone(), check.cluster(), resolver.clone());
+            let kind = config.enrichment.get(check.kind());
+            let check = Box::new(Named::new(check, namer)) as Box<dyn DynCheck>;
+
+            // events of clusters without any enrichment are left as they are
+            let enrichment = match (kind, enrichment) {
+                (None, None) => return check,
+                (Some(kind), None) => kind.clone(),
+                (None, Some(cluster)) => cluster.clone(),
+                (Some(kind), Some(cluster)) => kind.merge(cluster),
+            };
+            let enricher = Enricher::new(enrichment, check.cluster());
+            Box::new(Enriched::new(check, enricher)) as Box<dyn DynCheck>
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hosts_should_get_the_default_port() {
+        assert_eq!(host_port("pg1", 5432).unwrap(), ("pg1", 5432));
+        assert_eq!(host_port("10.0.0.1:6432", 1).unwrap(), ("10.0.0.1", 6432));
+        assert_eq!(host_port("fd00::7", 5432).unwrap(), ("fd00::7", 5432));
+        assert_eq!(host_port("[fd00::7]", 5432).unwrap(), ("fd00::7", 5432));
+        assert_eq!(host_port("[fd00::7]:6432", 1).unwrap(), ("fd00::7", 6432));
+        assert!(host_port("[fd00::7]6432", 5432).is_err());
+        assert!(host_port("pg1:port", 5432).is_err());
+    }
+
+    #[test]
+    fn addresses_should_get_the_default_port() {
+        assert_eq!(address("redis1", 6379), "redis1:6379");
+        assert_eq!(address("127.0.0.1:6380", 6379), "127.0.0.1:6380");
+        assert_eq!(address("::1", 6379), "[::1]:6379");
+        assert_eq!(address("[::1]", 6379), "[::1]:6379");
+        assert_eq!(address("[::1]:6380", 6379), "[::1]:6380");
+    }
+}
@@ -0,0 +1,445 @@
+use async_trait::async_trait;
+use sqlx::postgres::{PgConnectOptions, PgConnection};
+use sqlx::{ConnectOptions, Connection};
+use tokio::time::Duration;
+use tracing::debug;
+
+use crate::check::Check;
+use crate::checks::host_port;
+use crate::config::PostgresConfig;
+use crate::discovery::Hosts;
+use crate::juggler::{metrics, Event, Status};
+use crate::logging::{timed, Phase};
+use crate::secret::{self, Secret};
+use crate::BoxError;
+
+const POSTGRES_PORT: u16 = 5432;
+const TIMEOUT: u64 = 3;
+
+const REPLICATION_QUERY: &str = "
+    SELECT application_name,
+           coalesce(client_addr::text, ''),
+           state,
+           pg_wal_lsn_diff(pg_current_wal_lsn(), replay_lsn)::bigint,
+           extract(epoch FROM replay_lag)::float8
+    FROM pg_stat_replication";
+
+// the last replayed transaction only dates the lag while there is WAL left to replay,
+// a caught up replica of an idle primary is not lagging however old that transaction is
+const REPLAY_QUERY: &str = "
+    SELECT pg_wal_lsn_diff(pg_last_wal_receive_lsn(), pg_last_wal_replay_lsn())::bigint,
+           CASE WHEN pg_last_wal_receive_lsn() = pg_last_wal_replay_lsn() THEN 0
+                ELSE extract(epoch FROM now() - pg_last_xact_replay_timestamp())
+           END::float8";
+
+/// A row of `pg_stat_replication` on the primary.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replica {
+    pub name: String,
+    pub addr: String,
+    pub state: String,
+    pub lag_bytes: Option<i64>,
+    pub lag_seconds: Option<f64>,
+}
+
+/// Replication as one server sees it, by its role.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PostgresSample {
+    Primary {
+        replicas: Vec<Replica>,
+    },
+    Replica {
+        lag_bytes: Option<i64>,
+        lag_seconds: Option<f64>,
+    },
+}
+
+pub struct PostgresCheck {
+    cluster: String,
+    hosts: Hosts,
+    database: String,
+    login: String,
+    password: Secret,
+    expected_replicas: Option<usize>,
+    max_lag_bytes: i64,
+    max_lag_seconds: f64,
+}
+
+impl PostgresCheck {
+    pub fn new(config: &PostgresConfig) -> Result<Self, BoxError> {
+        let (login, password) = secret::resolve(&[&config.credentials])
+            .map_err(|e| format!("postgres/{}: {e}", config.cluster))?;
+        let label = format!("postgres/{}", config.cluster);
+
+        Ok(PostgresCheck {
+            cluster: config.cluster.clone(),
+            hosts: Hosts::new(label, config.hosts.clone(), config.discovery.as_ref())?,
+            database: config.database.clone(),
+            login,
+            password,
+            expected_replicas: config.expected_replicas,
+            max_lag_bytes: config.max_lag_bytes,
+            max_lag_seconds: config.max_lag_seconds,
+        })
+    }
+
+    fn options(&self, target: &str) -> Result<PgConnectOptions, BoxError> {
+        let (host, port) = host_port(target, POSTGRES_PORT)?;
+
+        let mut options = PgConnectOptions::new()
+            .host(host)
+            .port(port)
+            .username(&self.login)
+            .password(self.password.expose())
+            .database(&self.database);
+        options.disable_statement_logging();
+
+        Ok(options)
+    }
+
+    fn lagging(&self, lag_bytes: Option<i64>, lag_seconds: Option<f64>) -> bool {
+        lag_bytes.is_some_and(|b| b > self.max_lag_bytes)
+            || lag_seconds.is_some_and(|s| s > self.max_lag_seconds)
+    }
+
+    fn expected_replicas(&self) -> usize {
+        self.expected_replicas
+            .unwrap_or(self.hosts.count().saturating_sub(1))
+    }
+
+    fn compute(&self, host: &str, sample: &PostgresSample, primaries: usize) -> Event {
+        let mut values = vec![("primaries", primaries as f64)];
+
+        let (mut status, mut description) = match sample {
+            PostgresSample::Primary { replicas } => {
+                let mut description = format!(
+                    "primary. replicas: {}/{}",
+                    replicas.len(),
+                    self.expected_replicas()
+                );
+                for r in replicas {
+                    description = format!(
+                        "{description}\n{} ({}): {}, lag: {} bytes, {}s",
+                        r.name,
+                        r.addr,
+                        r.state,
+                        display(r.lag_bytes),
+                        display(r.lag_seconds.map(|s| s.round()))
+                    );
+                }
+
+                values.push(("replicas", replicas.len() as f64));
+                if let Some(lag) = replicas.iter().filter_map(|r| r.lag_bytes).max() {
+                    values.push(("max_lag_bytes", lag as f64));
+                }
+
+                let status = if replicas.len() < self.expected_replicas()
+                    || replicas
+                        .iter()
+                        .any(|r| self.lagging(r.lag_bytes, r.lag_seconds))
+                {
+                    Status::WARN
+                } else {
+                    Status::OK
+                };
+
+                (status, description)
+            }
+            PostgresSample::Replica {
+                lag_bytes,
+                lag_seconds,
+            } => {
+                let description = format!(
+                    "replica. replay lag: {} bytes, {}s",
+                    display(*lag_bytes),
+                    display(lag_seconds.map(|s| s.round()))
+                );
+
+                if let Some(lag) = lag_bytes {
+                    values.push(("lag_bytes", *lag as f64));
+                }
+                if let Some(lag) = lag_seconds {
+                    values.push(("lag_seconds", *lag));
+                }
+
+                let status = if self.lagging(*lag_bytes, *lag_seconds) {
+                    Status::WARN
+                } else {
+                    Status::OK
+                };
+
+                (status, description)
+            }
+        };
+
+        if primaries != 1 {
+            status = Status::CRIT;
+            description = format!("{description}\n{primaries} primaries in the cluster");
+        }
+
+        Event {
+            host: host.to_string(),
+            service: String::from("postgres-replication"),
+            instance: String::from(""),
+            status,
+            description,
+            tags: vec![String::from("postgres"), String::from("monitoring")],
+            metrics: metrics(&values),
+            cluster_level: false,
+        }
+    }
+}
+
+fn display<T: ToString>(value: Option<T>) -> String {
+    value.map_or(String::from("?"), |v| v.to_string())
+}
+
+#[async_trait]
+impl Check for PostgresCheck {
+    type Sample = PostgresSample;
+
+    fn kind(&self) -> &'static str {
+        "postgres"
+    }
+
+    fn cluster(&self) -> &str {
+        &self.cluster
+    }
+
+    async fn targets(&self) -> Vec<String> {
+        self.hosts.refresh().await
+    }
+
+    async fn collect(&self, target: &str) -> Result<PostgresSample, BoxError> {
+        debug!("connecting to {target}...");
+
+        let options = self.options(target)?;
+
+        let collect = async {
+            let mut conn = timed(Phase::Connect, PgConnection::connect_with(&options)).await?;
+
+            let (in_recovery,): (bool,) = timed(
+                Phase::Read,
+                sqlx::query_as("SELECT pg_is_in_recovery()").fetch_one(&mut conn),
+            )
+            .await?;
+
+            let sample = if in_recovery {
+                let (lag_bytes, lag_seconds): (Option<i64>, Option<f64>) = timed(
+                    Phase::Read,
+                    sqlx::query_as(REPLAY_QUERY).fetch_one(&mut conn),
+                )
+                .await?;
+
+                PostgresSample::Replica {
+                    lag_bytes,
+                    lag_seconds,
+                }
+            } else {
+                let rows: Vec<(String, String, String, Option<i64>, Option<f64>)> = timed(
+                    Phase::Read,
+                    sqlx::query_as(REPLICATION_QUERY).fetch_all(&mut conn),
+                )
+                .await?;
+
+                PostgresSample::Primary {
+                    replicas: rows
+                        .into_iter()
+                        .map(|(name, addr, state, lag_bytes, lag_seconds)| Replica {
+                            name,
+                            addr,
+                            state,
+                            lag_bytes,
+                            lag_seconds,
+                        })
+                        .collect(),
+                }
+            };
+
+            conn.close().await?;
+            Ok(sample)
+        };
+
+        tokio::time::timeout(Duration::from_secs(TIMEOUT), collect)
+            .await
+            .map_err(|_| format!("timed out collecting data from {target}"))?
+    }
+
+    fn evaluate(&self, samples: Vec<(String, PostgresSample)>) -> Vec<Event> {
+        let primaries = samples
+            .iter()
+            .filter(|(_, s)| matches!(s, PostgresSample::Primary { .. }))
+            .count();
+
+        samples
+            .iter()
+            .map(|(host, sample)| self.compute(host, sample, primaries))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret::Credentials;
+
+    fn check() -> PostgresCheck {
+        let config: PostgresConfig = toml::from_str(
+            r#"
+            cluster = "api"
+            hosts = ["pg1.example.com", "pg2.example.com", "pg3.example.com"]
+            max_lag_bytes = 1000
+            max_lag_seconds = 10.0
+
+            [credentials]
+            login = "monitoring"
+            password = "secret"
+            "#,
+        )
+        .unwrap();
+
+        PostgresCheck::new(&config).unwrap()
+    }
+
+    fn replica(name: &str, lag_bytes: i64) -> Replica {
+        Replica {
+            name: name.to_string(),
+            addr: String::from("10.0.0.2"),
+            state: String::from("streaming"),
+            lag_bytes: Some(lag_bytes),
+            lag_seconds: Some(0.2),
+        }
+    }
+
+    #[test]
+    fn healthy_primary_should_be_ok() {
+        let sample = PostgresSample::Primary {
+            replicas: vec![replica("pg2", 0), replica("pg3", 10)],
+        };
+
+        let event = check().compute("pg1.example.com", &sample, 1);
+
+        assert_eq!(event.host, "pg1.example.com");
+        assert_eq!(event.status, Status::OK);
+        assert_eq!(
+            event.description,
+            "primary. replicas: 2/2\npg2 (10.0.0.2): streaming, lag: 0 bytes, 0s\npg3 (10.0.0.2): streaming, lag: 10 bytes, 0s"
+        );
+    }
+
+    #[test]
+    fn lagging_replica_should_warn() {
+        let sample = PostgresSample::Primary {
+            replicas: vec![replica("pg2", 0), replica("pg3", 5000)],
+        };
+        assert_eq!(check().compute("pg1", &sample, 1).status, Status::WARN);
+
+        let sample = PostgresSample::Replica {
+            lag_bytes: Some(0),
+            lag_seconds: Some(60.0),
+        };
+        assert_eq!(check().compute("pg2", &sample, 1).status, Status::WARN);
+    }
+
+    #[test]
+    fn caught_up_replica_should_be_ok() {
+        // REPLAY_QUERY reports no lag when everything received is replayed
+        let sample = PostgresSample::Replica {
+            lag_bytes: Some(0),
+            lag_seconds: Some(0.0),
+        };
+        let event = check().compute("pg2", &sample, 1);
+
+        assert_eq!(event.status, Status::OK);
+        assert_eq!(event.description, "replica. replay lag: 0 bytes, 0s");
+        assert_eq!(event.metrics["lag_seconds"], 0.0);
+
+        // never replayed anything yet
+        let sample = PostgresSample::Replica {
+            lag_bytes: None,
+            lag_seconds: None,
+        };
+        let event = check().compute("pg2", &sample, 1);
+
+        assert_eq!(event.status, Status::OK);
+        assert_eq!(event.description, "replica. replay lag: ? bytes, ?s");
+    }
+
+    #[test]
+    fn missing_replicas_should_warn() {
+        let sample = PostgresSample::Primary {
+            replicas: vec![replica("pg2", 0)],
+        };
+        let event = check().compute("pg1", &sample, 1);
+
+        assert_eq!(event.status, Status::WARN);
+        assert!(event.description.starts_with("primary. replicas: 1/2\n"));
+    }
+
+    #[test]
+    fn replicas_without_primary_should_be_critical() {
+        let samples = vec![
+            (
+                String::from("pg2"),
+                PostgresSample::Replica {
+                    lag_bytes: Some(0),
+                    lag_seconds: Some(0.0),
+                },
+            ),
+            (
+                String::from("pg3"),
+                PostgresSample::Replica {
+                    lag_bytes: Some(0),
+                    lag_seconds: Some(0.0),
+                },
+            ),
+        ];
+
+        let events = check().evaluate(samples);
+
+        assert!(events.iter().all(|e| e.status == Status::CRIT));
+        assert_eq!(
+            events[0].description,
+            "replica. replay lag: 0 bytes, 0s\n0 primaries in the cluster"
+        );
+        assert_eq!(events[0].metrics["primaries"], 0.0);
+    }
+
+    #[test]
+    fn two_primaries_should_be_critical() {
+        let check = check();
+        let samples = vec![
+            (
+                String::from("pg1"),
+                PostgresSample::Primary { replicas: vec![] },
+            ),
+            (
+                String::from("pg2"),
+                PostgresSample::Primary { replicas: vec![] },
+            ),
+        ];
+
+        let events = check.evaluate(samples);
+
+        assert!(events.iter().all(|e| e.status == Status::CRIT));
+        assert!(events[0].description.ends_with("2 primaries in the cluster"));
+    }
+
+    #[test]
+    fn credentials_should_be_required() {
+        let config = PostgresConfig {
+            cluster: String::from("api"),
+            hosts: vec![],
+            discovery: None,
+            database: String::from("postgres"),
+            expected_replicas: None,
+            max_lag_bytes: 0,
+            max_lag_seconds: 0.0,
+            credentials: Credentials::default(),
+            naming: None,
+            enrichment: None,
+        };
+
+        assert!(PostgresCheck::new(&config).is_err());
+    }
+}
@@ -0,0 +1,362 @@
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use regex::Regex;
+use reqwest::{Client, ClientBuilder, Response, Url};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::check::Check;
+use crate::config::{HttpProbeConfig, TcpProbeConfig};
+use crate::juggler::{metrics, Event, Status};
+use crate::logging::{timed, timed_sync, Phase};
+use crate::BoxError;
+
+// how much of a banner or body is looked at
+const MAX_READ: usize = 64 * 1024;
+
+/// Outcome of probing one target. A failed probe is still a sample:
+/// an unreachable endpoint is exactly what the probe reports on.
+#[derive(Debug)]
+pub struct Probe {
+    pub latency: Duration,
+    pub failure: Option<String>,
+}
+
+/// The target is the instance, so two ports or URLs of one host stay apart.
+fn compute(
+    name: &str,
+    host: &str,
+    target: &str,
+    probe: &Probe,
+    max_latency: Option<Duration>,
+    tag: &str,
+) -> Event {
+    let latency = probe.latency.as_millis();
+
+    let (status, description) = match &probe.failure {
+        Some(failure) => (Status::CRIT, format!("failed in {latency}ms: {failure}")),
+        None => match max_latency {
+            Some(max) if probe.latency > max => (
+                Status::WARN,
+                format!("ok in {latency}ms, slower than {}ms", max.as_millis()),
+            ),
+            _ => (Status::OK, format!("ok in {latency}ms")),
+        },
+    };
+
+    Event {
+        host: host.to_string(),
+        service: name.to_string(),
+        instance: target.to_string(),
+        status,
+        description,
+        tags: vec![tag.to_string(), String::from("monitoring")],
+        metrics: metrics(&[("latency_ms", latency as f64)]),
+        cluster_level: false,
+    }
+}
+
+pub struct TcpProbe {
+    name: String,
+    hosts: Vec<String>,
+    send: Option<String>,
+    expect: Option<String>,
+    timeout: Duration,
+}
+
+impl TcpProbe {
+    pub fn new(config: &TcpProbeConfig) -> Self {
+        TcpProbe {
+            name: config.name.clone(),
+            hosts: config.hosts.clone(),
+            send: config.send.clone(),
+            expect: config.expect.clone(),
+            timeout: Duration::from_millis(config.timeout_ms),
+        }
+    }
+
+    async fn probe(&self, target: &str) -> Result<(), BoxError> {
+        let mut conn = timed(Phase::Connect, TcpStream::connect(target)).await?;
+
+        if let Some(send) = &self.send {
+            conn.write_all(send.as_bytes()).await?;
+        }
+
+        let Some(expect) = &self.expect else {
+            return Ok(());
+        };
+
+        timed(Phase::Read, read_until(&mut conn, expect)).await
+    }
+}
+
+/// Reads until `expect` shows up in the reply.
+async fn read_until(conn: &mut TcpStream, expect: &str) -> Result<(), BoxError> {
+    let mut banner = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        let n = conn.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(format!("connection closed before {expect:?} was received").into());
+        }
+
+        banner.extend_from_slice(&chunk[..n]);
+        if String::from_utf8_lossy(&banner).contains(expect) {
+            return Ok(());
+        }
+        if banner.len() > MAX_READ {
+            return Err(format!("{expect:?} not found in the first {MAX_READ} bytes").into());
+        }
+    }
+}
+
+#[async_trait]
+impl Check for TcpProbe {
+    type Sample = Probe;
+
+    fn kind(&self) -> &'static str {
+        "tcp"
+    }
+
+    fn cluster(&self) -> &str {
+        &self.name
+    }
+
+    async fn targets(&self) -> Vec<String> {
+        self.hosts.clone()
+    }
+
+    async fn collect(&self, target: &str) -> Result<Probe, BoxError> {
+        let started = Instant::now();
+
+        let failure = match tokio::time::timeout(self.timeout, self.probe(target)).await {
+            Err(_elapsed) => Some(format!("timed out after {}ms", self.timeout.as_millis())),
+            Ok(Err(e)) => Some(e.to_string()),
+            Ok(Ok(())) => None,
+        };
+
+        Ok(Probe {
+            latency: started.elapsed(),
+            failure,
+        })
+    }
+
+    fn evaluate(&self, samples: Vec<(String, Probe)>) -> Vec<Event> {
+        samples
+            .iter()
+            .map(|(target, probe)| compute(&self.name, target, target, probe, None, "tcp"))
+            .collect()
+    }
+}
+
+/// Turns a `$.a.b[0]` path into the `/a/b/0` JSON pointer.
+pub fn json_pointer(path: &str) -> Result<String, BoxError> {
+    let rest = path
+        .strip_prefix('$')
+        .ok_or_else(|| format!("json path {path:?} must start with $"))?;
+
+    let mut pointer = String::new();
+    let mut chars = rest.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let segment: String = match c {
+            '.' => {
+                let mut key = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c == '.' || c == '[' {
+                        break;
+                    }
+                    key.push(c);
+                    chars.next();
+                }
+                key
+            }
+            '[' => {
+                let index: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                index.trim_matches(['"', '\'']).to_string()
+            }
+            _ => return Err(format!("unexpected {c:?} in json path {path:?}").into()),
+        };
+
+        if segment.is_empty() {
+            return Err(format!("empty segment in json path {path:?}").into());
+        }
+        pointer.push('/');
+        pointer.push_str(&segment.replace('~', "~0").replace('/', "~1"));
+    }
+
+    Ok(pointer)
+}
+
+pub struct HttpProbe {
+    name: String,
+    urls: Vec<String>,
+    status: u16,
+    body: Option<Regex>,
+    json_path: Option<(String, String)>,
+    json_equals: Option<serde_json::Value>,
+    max_latency: Option<Duration>,
+    client: Client,
+}
+
+impl HttpProbe {
+    pub fn new(config: &HttpProbeConfig) -> Result<Self, BoxError> {
+        let body = match &config.body {
+            Some(body) => Some(Regex::new(body).map_err(|e| format!("http/{}: {e}", config.name))?),
+            None => None,
+        };
+
+        let json_path = match &config.json_path {
+            Some(path) => Some((
+                path.clone(),
+                json_pointer(path).map_err(|e| format!("http/{}: {e}", config.name))?,
+            )),
+            None if config.json_equals.is_some() => {
+                return Err(format!("http/{}: json_equals needs json_path", config.name).into())
+            }
+            None => None,
+        };
+
+        let client = ClientBuilder::new()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .danger_accept_invalid_certs(config.insecure)
+            .build()?;
+
+        Ok(HttpProbe {
+            name: config.name.clone(),
+            urls: config.urls.clone(),
+            status: config.status,
+            body,
+            json_path,
+            json_equals: config.json_equals.clone(),
+            max_latency: config.max_latency_ms.map(Duration::from_millis),
+            client,
+        })
+    }
+
+    async fn probe(&self, url: &str) -> Result<(), BoxError> {
+        let response = timed(Phase::Connect, self.client.get(url).send()).await?;
+
+        let status = response.status().as_u16();
+        let body = timed(Phase::Read, read_head(response)).await?;
+
+        if status != self.status {
+            return Err(format!("status {status}, expected {}", self.status).into());
+        }
+
+        if let Some(regex) = &self.body {
+            if !regex.is_match(&body) {
+                return Err(format!("body does not match {:?}", regex.as_str()).into());
+            }
+        }
+
+        if let Some((path, pointer)) = &self.json_path {
+            let json: serde_json::Value = timed_sync(Phase::Parse, || serde_json::from_str(&body))
+                .map_err(|e| format!("body is not JSON: {e}"))?;
+
+            let found = json
+                .pointer(pointer)
+                .ok_or_else(|| format!("{path} not found"))?;
+
+            if let Some(expected) = &self.json_equals {
+                if found != expected {
+                    return Err(format!("{path} is {found}, expected {expected}").into());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads the body chunk by chunk up to `MAX_READ`, the rest is never downloaded.
+async fn read_head(mut response: Response) -> Result<String, reqwest::Error> {
+    let mut head = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        head.extend_from_slice(&chunk);
+        if head.len() >= MAX_READ {
+            head.truncate(MAX_READ);
+            break;
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&head).into_owned())
+}
+
+#[async_trait]
+impl Check for HttpProbe {
+    type Sample = Probe;
+
+    fn kind(&self) -> &'static str {
+        "http"
+    }
+
+    fn cluster(&self) -> &str {
+        &self.name
+    }
+
+    async fn targets(&self) -> Vec<String> {
+        self.urls.clone()
+    }
+
+    async fn collect(&self, target: &str) -> Result<Probe, BoxError> {
+        let started = Instant::now();
+        let failure = self.probe(target).await.err().map(|e| e.to_string());
+
+        Ok(Probe {
+            latency: started.elapsed(),
+            failure,
+        })
+    }
+
+    fn evaluate(&self, samples: Vec<(String, Probe)>) -> Vec<Event> {
+        samples
+            .iter()
+            .map(|(url, probe)| {
+                let host = Url::parse(url)
+                    .ok()
+                    .and_then(|u| u.host_str().map(str::to_string))
+                    .unwrap_or_else(|| url.clone());
+
+                compute(&self.name, &host, url, probe, self.max_latency, "http")
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_path_should_become_pointer() {
+        assert_eq!(json_pointer("$").unwrap(), "");
+        assert_eq!(json_pointer("$.status").unwrap(), "/status");
+        assert_eq!(json_pointer("$.checks[0].name").unwrap(), "/checks/0/name");
+        assert_eq!(json_pointer("$['a/b']").unwrap(), "/a~1b");
+        assert!(json_pointer("status").is_err());
+        assert!(json_pointer("$..status").is_err());
+    }
+
+    #[test]
+    fn slow_probe_should_warn() {
+        let probe = Probe {
+            latency: Duration::from_millis(700),
+            failure: None,
+        };
+
+        let event = compute(
+            "api",
+            "api.example.com",
+            "https://api.example.com/health",
+            &probe,
+            Some(Duration::from_millis(500)),
+            "http",
+        );
+
+        assert_eq!(event.host, "api.example.com");
+        assert_eq!(event.status, Status::WARN);
+        assert_eq!(event.description, "ok in 700ms, slower than 500ms");
+    }
+}
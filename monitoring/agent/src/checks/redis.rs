@@ -0,0 +1,589 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::time::Duration;
+use tracing::debug;
+
+use crate::check::Check;
+use crate::checks::address;
+use crate::config::RedisConfig;
+use crate::discovery::Hosts;
+use crate::juggler::{metrics, Event, Status};
+use crate::logging::{timed, timed_sync, Phase};
+use crate::secret::Secret;
+use crate::state::State;
+use crate::BoxError;
+
+const REDIS_PORT: u16 = 6379;
+const TIMEOUT: u64 = 2000;
+
+/// A RESP reply.
+#[derive(Debug, PartialEq)]
+pub enum Value {
+    Simple(String),
+    Error(String),
+    Integer(i64),
+    Bulk(Option<String>),
+    Array(Option<Vec<Value>>),
+}
+
+impl Value {
+    fn as_string(&self) -> Option<String> {
+        match self {
+            Value::Simple(s) | Value::Bulk(Some(s)) => Some(s.clone()),
+            Value::Integer(i) => Some(i.to_string()),
+            _ => None,
+        }
+    }
+}
+
+fn encode(args: &[&str]) -> String {
+    let mut cmd = format!("*{}\r\n", args.len());
+    for arg in args {
+        cmd.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+    }
+    cmd
+}
+
+fn read_value(conn: &mut BufReader<TcpStream>) -> BoxFuture<'_, Result<Value, BoxError>> {
+    Box::pin(async move {
+        let mut line = String::new();
+        if conn.read_line(&mut line).await? == 0 {
+            return Err("connection closed".into());
+        }
+        let line = line.trim_end_matches("\r\n");
+
+        if line.is_empty() {
+            return Err("empty reply".into());
+        }
+        let (kind, rest) = line.split_at(1);
+
+        match kind {
+            "+" => Ok(Value::Simple(rest.to_string())),
+            "-" => Ok(Value::Error(rest.to_string())),
+            ":" => Ok(Value::Integer(rest.parse()?)),
+            "$" => {
+                let len: i64 = rest.parse()?;
+                if len < 0 {
+                    return Ok(Value::Bulk(None));
+                }
+
+                let mut data = vec![0u8; len as usize + 2];
+                conn.read_exact(&mut data).await?;
+                data.truncate(len as usize);
+                Ok(Value::Bulk(Some(String::from_utf8(data)?)))
+            }
+            "*" => {
+                let count: i64 = rest.parse()?;
+                if count < 0 {
+                    return Ok(Value::Array(None));
+                }
+
+                let mut items = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    items.push(read_value(conn).await?);
+                }
+                Ok(Value::Array(Some(items)))
+            }
+            _ => Err(format!("unexpected reply: {line}").into()),
+        }
+    })
+}
+
+pub async fn command(conn: &mut BufReader<TcpStream>, args: &[&str]) -> Result<Value, BoxError> {
+    conn.get_mut().write_all(encode(args).as_bytes()).await?;
+
+    match read_value(conn).await? {
+        Value::Error(e) => Err(format!("{}: {e}", args[0]).into()),
+        value => Ok(value),
+    }
+}
+
+/// `INFO` reply split into `key: value` pairs, section headers are skipped.
+pub fn parse_info(info: &str) -> HashMap<String, String> {
+    info.lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once(':'))
+        .map(|(k, v)| (k.to_string(), v.trim().to_string()))
+        .collect()
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Role {
+    Master {
+        offset: u64,
+        // replica address and acknowledged offset
+        replicas: Vec<(String, u64)>,
+    },
+    Replica {
+        master: String,
+        state: String,
+        // replication offset received from the master, unknown until the link is up
+        offset: Option<u64>,
+    },
+    Other(String),
+}
+
+pub fn parse_role(value: Value) -> Result<Role, BoxError> {
+    let items = match value {
+        Value::Array(Some(items)) if !items.is_empty() => items,
+        value => return Err(format!("unexpected ROLE reply: {value:?}").into()),
+    };
+
+    let field = |i: usize| {
+        items
+            .get(i)
+            .and_then(Value::as_string)
+            .ok_or_else(|| format!("ROLE reply has no field {i}"))
+    };
+
+    match field(0)?.as_str() {
+        "master" => {
+            let offset = field(1)?.parse()?;
+
+            let mut replicas = Vec::new();
+            if let Some(Value::Array(Some(list))) = items.get(2) {
+                for replica in list {
+                    let Value::Array(Some(replica)) = replica else {
+                        return Err("unexpected replica in ROLE reply".into());
+                    };
+                    let fields: Vec<String> = replica.iter().filter_map(Value::as_string).collect();
+                    if fields.len() < 3 {
+                        return Err("unexpected replica in ROLE reply".into());
+                    }
+                    replicas.push((format!("{}:{}", fields[0], fields[1]), fields[2].parse()?));
+                }
+            }
+
+            Ok(Role::Master { offset, replicas })
+        }
+        "slave" => Ok(Role::Replica {
+            master: format!("{}:{}", field(1)?, field(2)?),
+            state: field(3)?,
+            // -1 while connecting
+            offset: field(4).ok().and_then(|offset| offset.parse().ok()),
+        }),
+        role => Ok(Role::Other(role.to_string())),
+    }
+}
+
+/// `INFO` and `ROLE` replies of one server.
+#[derive(Debug)]
+pub struct RedisSample {
+    pub info: HashMap<String, String>,
+    pub role: Role,
+}
+
+impl RedisSample {
+    fn number(&self, key: &str) -> Option<u64> {
+        self.info.get(key).and_then(|v| v.parse().ok())
+    }
+}
+
+pub struct RedisCheck {
+    cluster: String,
+    hosts: Hosts,
+    expected_replicas: Option<usize>,
+    max_lag_bytes: u64,
+    max_last_io_seconds: u64,
+    memory_warn: f64,
+    memory_crit: f64,
+    // `AUTH` is sent first when the password is set, with the ACL user when the login is
+    login: Option<String>,
+    password: Option<Secret>,
+    // rejected_connections is a counter, so only growth between runs is alerted on
+    state: Arc<State>,
+}
+
+impl RedisCheck {
+    pub fn new(config: &RedisConfig) -> Result<Self, BoxError> {
+        let label = format!("redis/{}", config.cluster);
+        let credentials = |e| format!("redis/{}: {e}", config.cluster);
+        let login = config.credentials.login().map_err(credentials)?;
+        let password = config.credentials.password().map_err(credentials)?;
+        if login.is_some() && password.is_none() {
+            return Err(credentials(String::from("password is not set")).into());
+        }
+
+        Ok(RedisCheck {
+            cluster: config.cluster.clone(),
+            hosts: Hosts::new(label, config.hosts.clone(), config.discovery.as_ref())?,
+            expected_replicas: config.expected_replicas,
+            max_lag_bytes: config.max_lag_bytes,
+            max_last_io_seconds: config.max_last_io_seconds,
+            memory_warn: config.memory_warn,
+            memory_crit: config.memory_crit,
+            login,
+            password,
+            state: Arc::new(State::memory()),
+        })
+    }
+
+    /// Remembers the rejected connections in the agent state, so their growth is seen across restarts.
+    pub fn with_state(mut self, state: Arc<State>) -> Self {
+        self.state = state;
+        self
+    }
+
+    fn expected_replicas(&self) -> usize {
+        self.expected_replicas
+            .unwrap_or(self.hosts.count().saturating_sub(1))
+    }
+
+    fn event(
+        &self,
+        host: &str,
+        service: &str,
+        status: Status,
+        description: String,
+        values: &[(&str, f64)],
+    ) -> Event {
+        Event {
+            host: host.to_string(),
+            service: service.to_string(),
+            instance: String::from(""),
+            status,
+            description,
+            tags: vec![String::from("redis"), String::from("monitoring")],
+            metrics: metrics(values),
+            cluster_level: false,
+        }
+    }
+
+    /// `masters` are the replication offsets of every master of the run,
+    /// a replica's lag is counted from the only one.
+    fn replication(&self, host: &str, sample: &RedisSample, masters: &[u64]) -> Event {
+        let mut values = vec![("masters", masters.len() as f64)];
+
+        let (status, description) = match &sample.role {
+            Role::Master { offset, replicas } => {
+                let lag = replicas
+                    .iter()
+                    .map(|(_, acked)| offset.saturating_sub(*acked))
+                    .max()
+                    .unwrap_or(0);
+
+                values.push(("replicas", replicas.len() as f64));
+                values.push(("max_lag_bytes", lag as f64));
+
+                let mut description = format!(
+                    "master. replicas: {}/{}, max lag: {lag} bytes",
+                    replicas.len(),
+                    self.expected_replicas()
+                );
+
+                let status = if masters.len() != 1 {
+                    description =
+                        format!("{description}\n{} masters in the cluster", masters.len());
+                    Status::CRIT
+                } else if replicas.len() < self.expected_replicas() || lag > self.max_lag_bytes {
+                    Status::WARN
+                } else {
+                    Status::OK
+                };
+
+                (status, description)
+            }
+            Role::Replica {
+                master,
+                state,
+                offset,
+            } => {
+                let link = sample
+                    .info
+                    .get("master_link_status")
+                    .map(String::as_str)
+                    .unwrap_or("unknown");
+                let last_io = sample.number("master_last_io_seconds_ago");
+
+                let lag = match (masters, offset) {
+                    ([master], Some(offset)) => Some(master.saturating_sub(*offset)),
+                    _ => None,
+                };
+
+                let mut description = format!("replica of {master}. link: {link}, state: {state}");
+                if let Some(lag) = lag {
+                    values.push(("lag_bytes", lag as f64));
+                    description = format!("{description}, lag: {lag} bytes");
+                }
+                if let Some(last_io) = last_io {
+                    values.push(("last_io_seconds", last_io as f64));
+                    description = format!("{description}, last io: {last_io}s ago");
+                }
+
+                let status = if link != "up" || masters.is_empty() {
+                    Status::CRIT
+                } else if lag.is_some_and(|lag| lag > self.max_lag_bytes)
+                    || last_io.is_some_and(|s| s > self.max_last_io_seconds)
+                {
+                    Status::WARN
+                } else {
+                    Status::OK
+                };
+
+                (status, description)
+            }
+            Role::Other(role) => (Status::WARN, format!("unexpected role: {role}")),
+        };
+
+        self.event(host, "redis-replication", status, description, &values)
+    }
+
+    fn memory(&self, host: &str, sample: &RedisSample) -> Event {
+        let used = sample.number("used_memory").unwrap_or(0);
+
+        let max = sample.number("maxmemory").unwrap_or(0);
+        let values = [("used_memory", used as f64), ("maxmemory", max as f64)];
+
+        let (status, description) = match sample.number("maxmemory") {
+            Some(max) if max > 0 => {
+                let ratio = used as f64 / max as f64;
+                let status = if ratio >= self.memory_crit {
+                    Status::CRIT
+                } else if ratio >= self.memory_warn {
+                    Status::WARN
+                } else {
+                    Status::OK
+                };
+                (
+                    status,
+                    format!("used memory: {used}/{max} bytes ({:.0}%)", ratio * 100.0),
+                )
+            }
+            _ => (
+                Status::OK,
+                format!("used memory: {used} bytes, maxmemory is not set"),
+            ),
+        };
+
+        self.event(host, "redis-memory", status, description, &values)
+    }
+
+    fn clients(&self, host: &str, sample: &RedisSample) -> Event {
+        let rejected = sample.number("rejected_connections").unwrap_or(0);
+        let connected = sample.number("connected_clients").unwrap_or(0);
+
+        let key = format!("redis/{}/rejected", self.cluster);
+        let mut counters: HashMap<String, u64> = self.state.get(&key).unwrap_or_default();
+        let previous = counters.insert(host.to_string(), rejected);
+        self.state.set(&key, &counters);
+
+        // a restarted server starts counting from zero again
+        let new = match previous {
+            Some(previous) if rejected >= previous => rejected - previous,
+            _ => 0,
+        };
+
+        let status = if new > 0 { Status::WARN } else { Status::OK };
+        let description =
+            format!("connected clients: {connected}, rejected connections since last run: {new}");
+
+        let values = [
+            ("connected_clients", connected as f64),
+            ("rejected_connections", new as f64),
+        ];
+
+        self.event(host, "redis-clients", status, description, &values)
+    }
+}
+
+#[async_trait]
+impl Check for RedisCheck {
+    type Sample = RedisSample;
+
+    fn kind(&self) -> &'static str {
+        "redis"
+    }
+
+    fn cluster(&self) -> &str {
+        &self.cluster
+    }
+
+    async fn targets(&self) -> Vec<String> {
+        self.hosts.refresh().await
+    }
+
+    async fn collect(&self, target: &str) -> Result<RedisSample, BoxError> {
+        debug!("connecting to {target}...");
+
+        let collect = async {
+            let conn = timed(Phase::Connect, TcpStream::connect(address(target, REDIS_PORT))).await?;
+            let mut conn = BufReader::new(conn);
+
+            if let Some(password) = &self.password {
+                let mut auth = vec!["AUTH"];
+                auth.extend(self.login.as_deref());
+                auth.push(password.expose());
+                timed(Phase::Connect, command(&mut conn, &auth)).await?;
+            }
+
+            let info = match timed(Phase::Read, command(&mut conn, &["INFO"])).await? {
+                Value::Bulk(Some(info)) => timed_sync(Phase::Parse, || parse_info(&info)),
+                value => return Err(format!("unexpected INFO reply: {value:?}").into()),
+            };
+            let role = timed(Phase::Read, command(&mut conn, &["ROLE"])).await?;
+            let role = timed_sync(Phase::Parse, || parse_role(role))?;
+
+            Ok(RedisSample { info, role })
+        };
+
+        tokio::time::timeout(Duration::from_millis(TIMEOUT), collect)
+            .await
+            .map_err(|_| format!("timed out collecting data from {target}"))?
+    }
+
+    fn evaluate(&self, samples: Vec<(String, RedisSample)>) -> Vec<Event> {
+        let masters: Vec<u64> = samples
+            .iter()
+            .filter_map(|(_, s)| match s.role {
+                Role::Master { offset, .. } => Some(offset),
+                _ => None,
+            })
+            .collect();
+
+        samples
+            .iter()
+            .flat_map(|(host, sample)| {
+                [
+                    self.replication(host, sample, &masters),
+                    self.memory(host, sample),
+                    self.clients(host, sample),
+                ]
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check() -> RedisCheck {
+        RedisCheck::new(&RedisConfig {
+            cluster: String::from("cache"),
+            hosts: vec![String::from("redis1"), String::from("redis2")],
+            discovery: None,
+            expected_replicas: None,
+            max_lag_bytes: 100,
+            max_last_io_seconds: 10,
+            memory_warn: 0.8,
+            memory_crit: 0.95,
+            credentials: Default::default(),
+            naming: None,
+            enrichment: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn info_should_skip_sections() {
+        let info = parse_info("# Server\r\nredis_version:7.0.11\r\n\r\n# Memory\r\nused_memory:1024\r\n");
+
+        assert_eq!(info.len(), 2);
+        assert_eq!(info["used_memory"], "1024");
+    }
+
+    #[test]
+    fn master_role_should_parse() {
+        let role = parse_role(Value::Array(Some(vec![
+            Value::Bulk(Some(String::from("master"))),
+            Value::Integer(3129659),
+            Value::Array(Some(vec![Value::Array(Some(vec![
+                Value::Bulk(Some(String::from("10.0.0.2"))),
+                Value::Bulk(Some(String::from("6379"))),
+                Value::Bulk(Some(String::from("3129242"))),
+            ]))])),
+        ])))
+        .unwrap();
+
+        assert_eq!(
+            role,
+            Role::Master {
+                offset: 3129659,
+                replicas: vec![(String::from("10.0.0.2:6379"), 3129242)],
+            }
+        );
+    }
+
+    #[test]
+    fn lagging_replica_should_warn() {
+        let sample = RedisSample {
+            info: HashMap::new(),
+            role: Role::Master {
+                offset: 1000,
+                replicas: vec![(String::from("10.0.0.2:6379"), 500)],
+            },
+        };
+
+        let event = check().replication("redis1", &sample, &[1000]);
+
+        assert_eq!(event.status, Status::WARN);
+        assert_eq!(event.description, "master. replicas: 1/1, max lag: 500 bytes");
+    }
+
+    #[test]
+    fn replica_lag_should_be_counted_from_the_master_offset() {
+        let sample = RedisSample {
+            info: parse_info("master_link_status:up\r\nmaster_last_io_seconds_ago:1\r\n"),
+            role: Role::Replica {
+                master: String::from("10.0.0.1:6379"),
+                state: String::from("connected"),
+                offset: Some(800),
+            },
+        };
+
+        let event = check().replication("redis2", &sample, &[1000]);
+
+        assert_eq!(event.status, Status::WARN);
+        assert_eq!(
+            event.description,
+            "replica of 10.0.0.1:6379. link: up, state: connected, lag: 200 bytes, last io: 1s ago"
+        );
+        assert_eq!(event.metrics["lag_bytes"], 200.0);
+        assert_eq!(event.metrics["last_io_seconds"], 1.0);
+    }
+
+    #[test]
+    fn memory_should_be_compared_to_maxmemory() {
+        let sample = RedisSample {
+            info: parse_info("used_memory:960\r\nmaxmemory:1000\r\n"),
+            role: Role::Other(String::from("sentinel")),
+        };
+
+        assert_eq!(check().memory("redis1", &sample).status, Status::CRIT);
+    }
+
+    #[test]
+    fn rejected_connections_should_be_counted_between_runs() {
+        let check = check();
+        let sample = |rejected: u64| RedisSample {
+            info: parse_info(&format!("rejected_connections:{rejected}\r\n")),
+            role: Role::Other(String::from("sentinel")),
+        };
+
+        assert_eq!(check.clients("redis1", &sample(5)).status, Status::OK);
+        assert_eq!(check.clients("redis1", &sample(5)).status, Status::OK);
+        assert_eq!(check.clients("redis1", &sample(7)).status, Status::WARN);
+    }
+
+    #[test]
+    fn rejected_connections_should_be_counted_across_restarts() {
+        let state = Arc::new(State::memory());
+        let sample = |rejected: u64| RedisSample {
+            info: parse_info(&format!("rejected_connections:{rejected}\r\n")),
+            role: Role::Other(String::from("sentinel")),
+        };
+
+        let first = check().with_state(state.clone());
+        assert_eq!(first.clients("redis1", &sample(5)).status, Status::OK);
+
+        let restarted = check().with_state(state);
+        let event = restarted.clients("redis1", &sample(7));
+        assert_eq!(event.status, Status::WARN);
+        assert_eq!(event.metrics["rejected_connections"], 2.0);
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::{BTreeMap, HashSet};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::Client;
+use rmq::rmq::{self as api, build_client, Api, Info, Shovel, ShovelState, UpstreamStatus};
+use serde::de::DeserializeOwned;
+use tracing::warn;
+
+use crate::check::Check;
+use crate::config::RmqConfig;
+use crate::discovery::Hosts;
+use crate::juggler::{metrics, Event, Status};
+use crate::logging::{timed, timed_sync, Phase};
+use crate::naming::bare_host;
+use crate::secret::{self, Secret};
+use crate::BoxError;
+
+const TIMEOUT: u64 = 3;
+
+/// What a single node reported.
+#[derive(Debug, Default)]
+pub struct RmqSample {
+    pub federation: Vec<Info>,
+    pub shovels: Vec<Shovel>,
+}
+
+pub struct RmqCheck {
+    cluster: String,
+    hosts: Hosts,
+    api: Api,
+    client: Client,
+    login: String,
+    password: Secret,
+    federation: bool,
+    shovels: bool,
+}
+
+impl RmqCheck {
+    pub fn new(config: &RmqConfig) -> Result<Self, BoxError> {
+        let (login, password) = secret::resolve(&[&config.credentials])
+            .map_err(|e| format!("rmq/{}: {e}", config.cluster))?;
+        let label = format!("rmq/{}", config.cluster);
+        if config.tls.insecure {
+            warn!(
+                "rmq/{}: certificate verification is disabled",
+                config.cluster
+            );
+        }
+
+        Ok(RmqCheck {
+            cluster: config.cluster.clone(),
+            hosts: Hosts::new(label, config.hosts.clone(), config.discovery.as_ref())?,
+            api: Api {
+                scheme: config.scheme.clone(),
+                port: config.port,
+                path_prefix: config.path_prefix.clone(),
+            },
+            client: build_client(&config.tls, Duration::from_secs(TIMEOUT))
+                .map_err(|e| format!("rmq/{}: {e}", config.cluster))?,
+            login,
+            password,
+            federation: config.federation,
+            shovels: config.shovels,
+        })
+    }
+
+    async fn fetch<T: DeserializeOwned>(&self, host: &str, endpoint: &str) -> Result<T, BoxError> {
+        let request = self
+            .client
+            .get(self.api.url(host, endpoint))
+            .basic_auth(&self.login, Some(self.password.expose()))
+            .send();
+        let response = timed(Phase::Connect, request).await?.error_for_status()?;
+        let body = timed(Phase::Read, response.bytes()).await?;
+
+        // told apart from a broker that cannot be reached
+        timed_sync(Phase::Parse, || serde_json::from_slice(&body))
+            .map_err(|e| format!("failed to parse {endpoint} of {host}: {e}").into())
+    }
+}
+
+// the rmq monitor names its own hosts and has no metrics, the agent does both
+fn from_rmq(event: rmq::juggler::Event, host: &str, metrics: BTreeMap<String, f64>) -> Event {
+    Event {
+        host: host.to_string(),
+        service: event.service,
+        instance: event.instance,
+        status: match event.status {
+            rmq::juggler::Status::OK => Status::OK,
+            rmq::juggler::Status::WARN => Status::WARN,
+            rmq::juggler::Status::CRIT => Status::CRIT,
+        },
+        description: event.description,
+        tags: event.tags,
+        metrics,
+        cluster_level: false,
+    }
+}
+
+/// None for a host without federation links.
+pub fn compute(host: &str, info: &[Info]) -> Option<Event> {
+    let running = info
+        .iter()
+        .filter(|i| i.status == UpstreamStatus::Running)
+        .count();
+    let metrics = metrics(&[
+        ("upstreams", info.len() as f64),
+        ("running", running as f64),
+    ]);
+
+    Some(from_rmq(api::compute(info)?, host, metrics))
+}
+
+pub fn compute_shovel(host: &str, shovel: &Shovel) -> Event {
+    let running = if shovel.state == ShovelState::Running {
+        1.0
+    } else {
+        0.0
+    };
+
+    from_rmq(
+        api::compute_shovel(shovel),
+        host,
+        metrics(&[("running", running)]),
+    )
+}
+
+/// The polled host of the node a shovel runs on, so naming sees a configured host
+/// rather than a `rabbit@node` name. Falls back to the host that reported it.
+fn shovel_host<'a>(shovel: &Shovel, reported_by: &'a str, hosts: &[&'a str]) -> &'a str {
+    let node = shovel.node.split('@').next_back().unwrap();
+    let label = |host: &str| host.split('.').next().unwrap().to_string();
+
+    hosts
+        .iter()
+        .find(|host| label(bare_host(host)) == label(node))
+        .unwrap_or(&reported_by)
+}
+
+#[async_trait]
+impl Check for RmqCheck {
+    type Sample = RmqSample;
+
+    fn kind(&self) -> &'static str {
+        "rmq"
+    }
+
+    fn cluster(&self) -> &str {
+        &self.cluster
+    }
+
+    async fn targets(&self) -> Vec<String> {
+        self.hosts.refresh().await
+    }
+
+    async fn collect(&self, target: &str) -> Result<RmqSample, BoxError> {
+        let mut sample = RmqSample::default();
+
+        if self.federation {
+            sample.federation = self.fetch(target, "federation-links").await?;
+        }
+        if self.shovels {
+            sample.shovels = self.fetch(target, "shovels").await?;
+        }
+
+        Ok(sample)
+    }
+
+    fn evaluate(&self, samples: Vec<(String, RmqSample)>) -> Vec<Event> {
+        let mut events: Vec<Event> = samples
+            .iter()
+            .filter_map(|(host, sample)| compute(host, &sample.federation))
+            .collect();
+
+        // every node reports shovels of the whole cluster, so keep each one once
+        let hosts: Vec<&str> = samples.iter().map(|(host, _)| host.as_str()).collect();
+        let mut seen = HashSet::new();
+        events.extend(
+            samples
+                .iter()
+                .flat_map(|(host, sample)| sample.shovels.iter().map(move |s| (host, s)))
+                .filter(|(_, s)| seen.insert((&s.node, &s.vhost, &s.name)))
+                .map(|(host, s)| compute_shovel(shovel_host(s, host, &hosts), s)),
+        );
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shovels_should_get_the_polled_host_of_their_node() {
+        let shovel: Shovel = serde_json::from_str(
+            r#"{"node": "rabbit@rmq2", "name": "orders", "vhost": "/", "type": "dynamic", "state": "running"}"#,
+        )
+        .unwrap();
+        let hosts = ["rmq1.example.com:15672", "rmq2.example.com:15672"];
+
+        assert_eq!(shovel_host(&shovel, hosts[0], &hosts), hosts[1]);
+        // a node that is not polled is left to the host that reported it
+        assert_eq!(shovel_host(&shovel, hosts[0], &hosts[..1]), hosts[0]);
+    }
+}
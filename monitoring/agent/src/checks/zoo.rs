@@ -0,0 +1,610 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::time::Duration;
+use tracing::debug;
+
+use crate::check::Check;
+use crate::checks::address;
+use crate::config::ZooConfig;
+use crate::discovery::Hosts;
+use crate::juggler::{metrics, Event, Status};
+use crate::logging::{timed, timed_sync, Phase};
+use crate::state::State;
+use crate::BoxError;
+
+const ZOO_CMD: &str = "mntr";
+const ZOO_PORT: u16 = 2181;
+const CONNECT_TIMEOUT: u64 = 2000;
+const READ_TIMEOUT: u64 = 1000;
+
+/// Why a member could not be collected. Every variant names the host,
+/// so the error reads well on its own in logs and event descriptions.
+#[derive(Debug)]
+pub enum CollectError {
+    ConnectTimeout { host: String },
+    Connect { host: String, source: io::Error },
+    Write { host: String, source: io::Error },
+    ReadTimeout { host: String },
+    Read { host: String, source: io::Error },
+    Parse { host: String, source: BoxError },
+}
+
+impl CollectError {
+    pub fn host(&self) -> &str {
+        match self {
+            Self::ConnectTimeout { host }
+            | Self::Connect { host, .. }
+            | Self::Write { host, .. }
+            | Self::ReadTimeout { host }
+            | Self::Read { host, .. }
+            | Self::Parse { host, .. } => host,
+        }
+    }
+
+    /// CRIT when the member could not be reached, WARN when it answered but the reply is unusable.
+    pub fn status(&self) -> Status {
+        match self {
+            Self::ConnectTimeout { .. } | Self::Connect { .. } => Status::CRIT,
+            _ => Status::WARN,
+        }
+    }
+
+    /// Timeouts and broken connections may pass on another attempt. A refused
+    /// connection or a reply that does not parse will not.
+    pub fn is_transient(&self) -> bool {
+        !matches!(self, Self::Connect { .. } | Self::Parse { .. })
+    }
+}
+
+impl fmt::Display for CollectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ConnectTimeout { host } => write!(f, "timed out opening connection to {host}"),
+            Self::Connect { host, source } => write!(f, "failed to connect to {host}: {source}"),
+            Self::Write { host, source } => {
+                write!(f, "failed to write command to {host}: {source}")
+            }
+            Self::ReadTimeout { host } => write!(f, "timed out reading data from {host}"),
+            Self::Read { host, source } => write!(f, "failed to read data from {host}: {source}"),
+            Self::Parse { host, source } => write!(f, "failed to parse reply of {host}: {source}"),
+        }
+    }
+}
+
+impl Error for CollectError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Connect { source, .. }
+            | Self::Write { source, .. }
+            | Self::Read { source, .. } => Some(source),
+            Self::Parse { source, .. } => Some(source.as_ref()),
+            Self::ConnectTimeout { .. } | Self::ReadTimeout { .. } => None,
+        }
+    }
+}
+
+/// Sends a four letter word command and reads the reply until the server closes the connection.
+pub async fn four_letter_word(host: &str, cmd: &str) -> Result<String, CollectError> {
+    debug!("connecting to {host}...");
+
+    let mut conn = timed(
+        Phase::Connect,
+        tokio::time::timeout(
+            Duration::from_millis(CONNECT_TIMEOUT),
+            TcpStream::connect(address(host, ZOO_PORT)),
+        ),
+    )
+    .await
+    .map_err(|_| CollectError::ConnectTimeout {
+        host: host.to_string(),
+    })?
+    .map_err(|source| CollectError::Connect {
+        host: host.to_string(),
+        source,
+    })?;
+
+    let mut buf = String::new();
+    let read = async {
+        conn.write_all(cmd.as_bytes())
+            .await
+            .map_err(|source| CollectError::Write {
+                host: host.to_string(),
+                source,
+            })?;
+        BufReader::new(conn)
+            .read_to_string(&mut buf)
+            .await
+            .map_err(|source| CollectError::Read {
+                host: host.to_string(),
+                source,
+            })
+    };
+
+    timed(
+        Phase::Read,
+        tokio::time::timeout(Duration::from_millis(READ_TIMEOUT), read),
+    )
+    .await
+    .map_err(|_| CollectError::ReadTimeout {
+        host: host.to_string(),
+    })??;
+
+    debug!("got data from {host}");
+    Ok(buf)
+}
+
+/// Parsed `mntr` reply.
+#[derive(Debug)]
+pub struct Mntr(HashMap<String, String>);
+
+impl Mntr {
+    pub fn parse(info: &str) -> Result<Self, BoxError> {
+        let metrics: HashMap<String, String> = info
+            .lines()
+            // truncated replies may end with a partial line
+            .filter_map(|line| line.split_once('\t'))
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+
+        if !metrics.contains_key("zk_server_state") {
+            return Err("zk_server_state is missing".into());
+        }
+
+        Ok(Mntr(metrics))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    /// Numeric values of the reply.
+    pub fn numbers(&self) -> BTreeMap<String, f64> {
+        self.0
+            .iter()
+            .filter_map(|(k, v)| v.parse().ok().map(|v| (k.clone(), v)))
+            .collect()
+    }
+
+    pub fn state(&self) -> &str {
+        self.get("zk_server_state").unwrap_or_default()
+    }
+}
+
+/// What the previous runs have seen of the ensemble, kept in the agent state.
+#[derive(Default, Serialize, Deserialize)]
+struct Election {
+    leader: Option<String>,
+    states: HashMap<String, String>,
+    // when the leader changed, oldest first
+    changes: VecDeque<SystemTime>,
+}
+
+pub struct ZooCheck {
+    cluster: String,
+    hosts: Hosts,
+    expected_followers: Option<usize>,
+    expected_observers: usize,
+    tags: Vec<String>,
+    max_leader_changes: usize,
+    leader_changes_window: Duration,
+    retries: u32,
+    state: Arc<State>,
+}
+
+impl ZooCheck {
+    pub fn new(config: &ZooConfig) -> Result<Self, BoxError> {
+        let label = format!("zoo/{}", config.cluster);
+
+        Ok(ZooCheck {
+            cluster: config.cluster.clone(),
+            hosts: Hosts::new(label, config.hosts.clone(), config.discovery.as_ref())?,
+            expected_followers: config.expected_followers,
+            expected_observers: config.expected_observers,
+            tags: config.tags.clone(),
+            max_leader_changes: config.max_leader_changes,
+            leader_changes_window: Duration::from_secs(config.leader_changes_window),
+            retries: config.retries,
+            state: Arc::new(State::memory()),
+        })
+    }
+
+    /// Remembers the election in the agent state, so a leader change is seen across restarts.
+    pub fn with_state(mut self, state: Arc<State>) -> Self {
+        self.state = state;
+        self
+    }
+
+    fn expected_followers(&self) -> usize {
+        self.expected_followers.unwrap_or(
+            self.hosts
+                .count()
+                .saturating_sub(self.expected_observers + 1),
+        )
+    }
+
+    /// Compares the leader and member states with the previous run.
+    /// The event is sent for the whole ensemble, so its host is the cluster name.
+    fn election(&self, samples: &[(String, Mntr)], now: SystemTime) -> Event {
+        let key = format!("zoo/{}/election", self.cluster);
+        let mut election: Election = self.state.get(&key).unwrap_or_default();
+
+        let leader = samples
+            .iter()
+            .find(|(_, mntr)| matches!(mntr.state(), "leader" | "standalone"))
+            .map(|(host, _)| host.clone());
+
+        let mut transitions = Vec::new();
+        for (host, mntr) in samples {
+            let previous = election
+                .states
+                .insert(host.clone(), mntr.state().to_string());
+            match previous {
+                Some(previous) if previous != mntr.state() => {
+                    transitions.push(format!("{host}: {previous} -> {}", mntr.state()))
+                }
+                _ => {}
+            }
+        }
+
+        let mut description = match (&election.leader, &leader) {
+            (Some(old), Some(new)) if old != new => {
+                election.changes.push_back(now);
+                format!("leader changed: {old} -> {new}")
+            }
+            (_, Some(new)) => format!("leader: {new}"),
+            // keep the last known leader to spot the change once the election is over
+            (_, None) => String::from("no leader"),
+        };
+        if leader.is_some() {
+            election.leader = leader;
+        }
+
+        while election.changes.front().is_some_and(|at| {
+            now.duration_since(*at)
+                .is_ok_and(|age| age > self.leader_changes_window)
+        }) {
+            election.changes.pop_front();
+        }
+        self.state.set(&key, &election);
+
+        let changes = election.changes.len();
+        description = format!(
+            "{description}, leader changes in the last {}s: {changes}/{}",
+            self.leader_changes_window.as_secs(),
+            self.max_leader_changes
+        );
+        for transition in transitions {
+            description = format!("{description}\n{transition}");
+        }
+
+        Event {
+            host: self.cluster.clone(),
+            service: String::from("leader-election"),
+            instance: String::from(""),
+            status: if changes > self.max_leader_changes {
+                Status::WARN
+            } else {
+                Status::OK
+            },
+            description,
+            tags: self.tags.clone(),
+            metrics: metrics(&[("leader_changes", changes as f64)]),
+            cluster_level: true,
+        }
+    }
+
+    fn compute(&self, host: &str, mntr: &Mntr, leaders: usize) -> Event {
+        let (mut status, mut description) = if mntr.state() == "leader" {
+            match mntr.get("zk_synced_followers").map(str::parse::<usize>) {
+                Some(Ok(followers)) => {
+                    let expected_followers = self.expected_followers();
+                    let mut status = if followers == expected_followers {
+                        Status::OK
+                    } else {
+                        Status::WARN
+                    };
+                    let mut description =
+                        format!("leader. followers: {followers}/{expected_followers}");
+
+                    if self.expected_observers > 0 {
+                        let observers = mntr
+                            .get("zk_synced_observers")
+                            .and_then(|o| o.parse::<usize>().ok())
+                            .unwrap_or(0);
+                        if observers != self.expected_observers {
+                            status = Status::WARN;
+                        }
+                        description = format!(
+                            "{description}, observers: {observers}/{}",
+                            self.expected_observers
+                        );
+                    }
+
+                    (status, description)
+                }
+                _ => (
+                    Status::WARN,
+                    String::from("leader. zk_synced_followers is missing"),
+                ),
+            }
+        } else {
+            (Status::OK, mntr.state().to_string())
+        };
+
+        if leaders != 1 {
+            status = Status::CRIT;
+            description = format!("{description}\n{leaders} leaders in the ensemble");
+        }
+
+        Event {
+            host: host.to_string(),
+            service: String::from("state"),
+            instance: String::from(""),
+            status,
+            description,
+            tags: self.tags.clone(),
+            metrics: mntr.numbers(),
+            cluster_level: false,
+        }
+    }
+}
+
+#[async_trait]
+impl Check for ZooCheck {
+    type Sample = Mntr;
+
+    fn kind(&self) -> &'static str {
+        "zoo"
+    }
+
+    fn cluster(&self) -> &str {
+        &self.cluster
+    }
+
+    async fn targets(&self) -> Vec<String> {
+        self.hosts.refresh().await
+    }
+
+    async fn collect(&self, target: &str) -> Result<Mntr, BoxError> {
+        let info = four_letter_word(target, ZOO_CMD).await?;
+        let mntr = timed_sync(Phase::Parse, || Mntr::parse(&info));
+
+        Ok(mntr.map_err(|source| CollectError::Parse {
+            host: target.to_string(),
+            source,
+        })?)
+    }
+
+    fn retry(&self, error: &BoxError, attempt: u32) -> bool {
+        attempt <= self.retries
+            && error
+                .downcast_ref::<CollectError>()
+                .is_some_and(CollectError::is_transient)
+    }
+
+    fn failed(&self, error: &BoxError) -> Option<Event> {
+        let error = error.downcast_ref::<CollectError>()?;
+
+        Some(Event {
+            host: error.host().to_string(),
+            service: String::from("state"),
+            instance: String::from(""),
+            status: error.status(),
+            description: error.to_string(),
+            tags: self.tags.clone(),
+            metrics: BTreeMap::new(),
+            cluster_level: false,
+        })
+    }
+
+    fn evaluate(&self, samples: Vec<(String, Mntr)>) -> Vec<Event> {
+        // a standalone server leads an ensemble of one
+        let leaders = samples
+            .iter()
+            .filter(|(_, mntr)| matches!(mntr.state(), "leader" | "standalone"))
+            .count();
+
+        let mut events: Vec<Event> = samples
+            .iter()
+            .map(|(host, mntr)| self.compute(host, mntr, leaders))
+            .collect();
+        events.push(self.election(&samples, SystemTime::now()));
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check() -> ZooCheck {
+        let config: ZooConfig = toml::from_str(
+            r#"
+            cluster = "main"
+            hosts = ["zoo1.example.com", "zoo2.example.com", "zoo3.example.com"]
+            "#,
+        )
+        .unwrap();
+
+        ZooCheck::new(&config).unwrap()
+    }
+
+    #[test]
+    fn leader_should_count_followers() {
+        let mntr = Mntr::parse("zk_server_state\tleader\nzk_synced_followers\t1\n").unwrap();
+        let event = check().compute("zoo1.example.com", &mntr, 1);
+
+        assert_eq!(event.host, "zoo1.example.com");
+        assert_eq!(event.status, Status::WARN);
+        assert_eq!(event.description, "leader. followers: 1/2");
+    }
+
+    #[test]
+    fn follower_should_be_ok() {
+        let mntr = Mntr::parse("zk_server_state\tfollower\n").unwrap();
+        let event = check().compute("zoo2.example.com", &mntr, 1);
+
+        assert_eq!(event.status, Status::OK);
+        assert_eq!(event.description, "follower");
+    }
+
+    #[test]
+    fn observers_should_be_counted() {
+        let config: ZooConfig = toml::from_str(
+            r#"
+            cluster = "edge"
+            hosts = ["zoo1", "zoo2", "zoo3", "zoo4"]
+            expected_observers = 1
+            tags = ["zoo", "edge"]
+            "#,
+        )
+        .unwrap();
+        let mntr = Mntr::parse(
+            "zk_server_state\tleader\nzk_synced_followers\t2\nzk_synced_observers\t0\n",
+        )
+        .unwrap();
+
+        let event = ZooCheck::new(&config).unwrap().compute("zoo1", &mntr, 1);
+
+        assert_eq!(event.status, Status::WARN);
+        assert_eq!(event.description, "leader. followers: 2/2, observers: 0/1");
+        assert_eq!(event.tags, vec!["zoo", "edge"]);
+    }
+
+    #[test]
+    fn ensemble_without_leader_should_be_critical() {
+        let samples = vec![
+            (
+                String::from("zoo2"),
+                Mntr::parse("zk_server_state\tfollower\n").unwrap(),
+            ),
+            (
+                String::from("zoo3"),
+                Mntr::parse("zk_server_state\tfollower\n").unwrap(),
+            ),
+        ];
+
+        let events = check().evaluate(samples);
+
+        assert!(events[..2].iter().all(|e| e.status == Status::CRIT));
+        assert_eq!(events[0].description, "follower\n0 leaders in the ensemble");
+        assert_eq!(events[2].service, "leader-election");
+        assert!(events[2].description.starts_with("no leader"));
+    }
+
+    fn ensemble(leader: &str) -> Vec<(String, Mntr)> {
+        ["zoo1", "zoo2", "zoo3"]
+            .iter()
+            .map(|host| {
+                let state = if *host == leader { "leader" } else { "follower" };
+                let mntr = Mntr::parse(&format!("zk_server_state\t{state}\n")).unwrap();
+                (host.to_string(), mntr)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn frequent_leader_changes_should_warn() {
+        let check = check();
+        let start = SystemTime::now();
+        let minute = Duration::from_secs(60);
+
+        let event = check.election(&ensemble("zoo1"), start);
+        assert_eq!(event.host, "main");
+        assert_eq!(event.status, Status::OK);
+        assert_eq!(
+            event.description,
+            "leader: zoo1, leader changes in the last 3600s: 0/2"
+        );
+
+        check.election(&ensemble("zoo2"), start + minute);
+        check.election(&ensemble("zoo3"), start + 2 * minute);
+        let event = check.election(&ensemble("zoo1"), start + 3 * minute);
+
+        assert_eq!(event.status, Status::WARN);
+        assert_eq!(
+            event.description,
+            "leader changed: zoo3 -> zoo1, leader changes in the last 3600s: 3/2\nzoo1: follower -> leader\nzoo3: leader -> follower"
+        );
+
+        // the first changes leave the window
+        let event = check.election(&ensemble("zoo1"), start + 63 * minute);
+        assert_eq!(event.status, Status::OK);
+        assert_eq!(event.metrics["leader_changes"], 1.0);
+    }
+
+    #[test]
+    fn lost_leader_should_be_remembered() {
+        let check = check();
+        let start = SystemTime::now();
+        let no_leader: Vec<(String, Mntr)> = ensemble("zoo1")
+            .into_iter()
+            .filter(|(host, _)| host != "zoo1")
+            .collect();
+
+        check.election(&ensemble("zoo1"), start);
+        check.election(&no_leader, start);
+        let event = check.election(&ensemble("zoo2"), start);
+
+        assert!(event
+            .description
+            .starts_with("leader changed: zoo1 -> zoo2"));
+    }
+
+    #[test]
+    fn garbage_should_not_parse() {
+        assert!(Mntr::parse("garbage without tabs\n").is_err());
+    }
+
+    #[test]
+    fn only_transient_errors_should_be_retried() {
+        let host = || String::from("zoo1:2181");
+        let refused = CollectError::Connect {
+            host: host(),
+            source: io::Error::from(io::ErrorKind::ConnectionRefused),
+        };
+        let garbage = CollectError::Parse {
+            host: host(),
+            source: "zk_server_state is missing".into(),
+        };
+        let timeout = || BoxError::from(CollectError::ReadTimeout { host: host() });
+        let check = check();
+
+        assert!(check.retry(&timeout(), 1));
+        assert!(!check.retry(&timeout(), 2));
+        assert!(!check.retry(&BoxError::from(refused), 1));
+        assert!(!check.retry(&BoxError::from(garbage), 1));
+        assert!(!check.retry(&BoxError::from("not a collect error"), 1));
+    }
+
+    #[test]
+    fn errors_should_name_the_host_and_keep_the_cause() {
+        let error = CollectError::Read {
+            host: String::from("zoo1:2181"),
+            source: io::Error::from(io::ErrorKind::ConnectionReset),
+        };
+
+        assert_eq!(error.host(), "zoo1:2181");
+        assert_eq!(error.status(), Status::WARN);
+        assert!(error
+            .to_string()
+            .starts_with("failed to read data from zoo1:2181: "));
+        assert_eq!(
+            error
+                .source()
+                .and_then(|e| e.downcast_ref::<io::Error>())
+                .map(io::Error::kind),
+            Some(io::ErrorKind::ConnectionReset)
+        );
+    }
+}
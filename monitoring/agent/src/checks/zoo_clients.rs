@@ -0,0 +1,469 @@
+use std::collections::{BTreeMap, HashMap};
+
+use async_trait::async_trait;
+
+use crate::check::Check;
+use crate::checks::zoo::{four_letter_word, CollectError};
+use crate::config::{ZooClientsConfig, ZooConfig};
+use crate::discovery::Hosts;
+use crate::juggler::{metrics, Event, Status};
+use crate::logging::{timed_sync, Phase};
+use crate::BoxError;
+
+/// Connections and watches of one client or of a whole member.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Usage {
+    pub connections: usize,
+    pub watches: usize,
+}
+
+/// Client sessions of one member, keyed by client IP.
+#[derive(Debug, Default)]
+pub struct Clients {
+    pub total: Usage,
+    pub by_ip: BTreeMap<String, Usage>,
+}
+
+/// Client IP and session id of every session in a `cons` reply.
+/// The connection asking has no session and is left out.
+pub fn parse_cons(reply: &str) -> Result<Vec<(String, String)>, BoxError> {
+    if reply.trim().is_empty() {
+        return Err("empty reply".into());
+    }
+
+    let mut sessions = Vec::new();
+    for line in reply.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        // e.g. `/10.0.0.5:41234[1](queued=0,...,sid=0x100000bd5c50000,...)`
+        let Some((address, stats)) = line.strip_prefix('/').and_then(|l| l.split_once('(')) else {
+            return Err(format!("unexpected line {line:?}").into());
+        };
+
+        // truncated replies may end with a partial line
+        let Some(sid) = stats
+            .trim_end_matches(')')
+            .split(',')
+            .find_map(|stat| stat.strip_prefix("sid="))
+        else {
+            continue;
+        };
+
+        // the address is followed by the interest ops, IPv6 ones may be in brackets
+        let address = address.rsplit_once('[').map_or(address, |(a, _)| a);
+        let Some((ip, _port)) = address.rsplit_once(':') else {
+            return Err(format!("unexpected address {address:?}").into());
+        };
+        let ip = ip.trim_start_matches('[').trim_end_matches(']');
+
+        sessions.push((ip.to_string(), sid.to_string()));
+    }
+
+    Ok(sessions)
+}
+
+/// The watch total of a `wchs` reply.
+pub fn parse_wchs(reply: &str) -> Result<usize, BoxError> {
+    reply
+        .lines()
+        .find_map(|line| line.strip_prefix("Total watches:"))
+        .ok_or("Total watches is missing")?
+        .trim()
+        .parse()
+        .map_err(|e| format!("invalid Total watches: {e}").into())
+}
+
+/// Watched paths per session id of a `wchc` reply.
+pub fn parse_wchc(reply: &str) -> Result<HashMap<String, usize>, BoxError> {
+    let mut watches = HashMap::new();
+    let mut session = None;
+
+    for line in reply.lines().filter(|l| !l.trim().is_empty()) {
+        if line.starts_with('\t') {
+            let Some(sid) = &session else {
+                return Err(format!("path {:?} without a session", line.trim()).into());
+            };
+            *watches.entry(String::clone(sid)).or_default() += 1;
+        } else if line.starts_with("0x") {
+            watches.entry(line.trim().to_string()).or_insert(0);
+            session = Some(line.trim().to_string());
+        } else {
+            return Err(format!("unexpected line {line:?}").into());
+        }
+    }
+
+    Ok(watches)
+}
+
+/// Spots clients hogging the ensemble from `cons`, `wchs` and, unless disabled, `wchc`.
+pub struct ZooClientsCheck {
+    cluster: String,
+    hosts: Hosts,
+    tags: Vec<String>,
+    max_client_connections: usize,
+    max_client_watches: usize,
+    max_member_connections: usize,
+    max_member_watches: usize,
+    watches_per_client: bool,
+    top: usize,
+    retries: u32,
+}
+
+impl ZooClientsCheck {
+    pub fn new(config: &ZooConfig, clients: &ZooClientsConfig) -> Result<Self, BoxError> {
+        let label = format!("zoo-clients/{}", config.cluster);
+
+        Ok(ZooClientsCheck {
+            cluster: config.cluster.clone(),
+            hosts: Hosts::new(label, config.hosts.clone(), config.discovery.as_ref())?,
+            tags: config.tags.clone(),
+            max_client_connections: clients.max_client_connections,
+            max_client_watches: clients.max_client_watches,
+            max_member_connections: clients.max_member_connections,
+            max_member_watches: clients.max_member_watches,
+            watches_per_client: clients.watches_per_client,
+            top: clients.top,
+            retries: config.retries,
+        })
+    }
+
+    fn usage(&self, usage: &Usage) -> String {
+        if self.watches_per_client {
+            format!(
+                "{} connections, {} watches",
+                usage.connections, usage.watches
+            )
+        } else {
+            format!("{} connections", usage.connections)
+        }
+    }
+
+    fn client_exceeds(&self, usage: &Usage) -> bool {
+        usage.connections > self.max_client_connections
+            || (self.watches_per_client && usage.watches > self.max_client_watches)
+    }
+
+    /// The `top` clients closest to or furthest over the per-client limits.
+    fn offenders<'a>(&self, by_ip: &'a BTreeMap<String, Usage>) -> Vec<(&'a String, &'a Usage)> {
+        let load = |usage: &Usage| {
+            let connections = usage.connections as f64 / self.max_client_connections as f64;
+            let watches = if self.watches_per_client {
+                usage.watches as f64 / self.max_client_watches as f64
+            } else {
+                0.0
+            };
+            connections.max(watches)
+        };
+
+        let mut clients: Vec<_> = by_ip.iter().collect();
+        clients.sort_by(|a, b| load(b.1).total_cmp(&load(a.1)));
+        clients.truncate(self.top);
+        clients
+    }
+
+    fn describe(&self, description: String, by_ip: &BTreeMap<String, Usage>) -> String {
+        let mut description = description;
+        for (ip, usage) in self.offenders(by_ip) {
+            description = format!("{description}\n{ip}: {}", self.usage(usage));
+        }
+        description
+    }
+
+    fn member(&self, host: &str, clients: &Clients) -> Event {
+        let total = &clients.total;
+        let status = if total.connections > self.max_member_connections
+            || total.watches > self.max_member_watches
+        {
+            Status::WARN
+        } else {
+            Status::OK
+        };
+        let description = format!(
+            "connections: {}/{}, watches: {}/{}",
+            total.connections, self.max_member_connections, total.watches, self.max_member_watches
+        );
+
+        Event {
+            host: host.to_string(),
+            service: String::from("clients"),
+            instance: String::from(""),
+            status,
+            description: self.describe(description, &clients.by_ip),
+            tags: self.tags.clone(),
+            metrics: metrics(&[
+                ("connections", total.connections as f64),
+                ("watches", total.watches as f64),
+                ("clients", clients.by_ip.len() as f64),
+            ]),
+            cluster_level: false,
+        }
+    }
+
+    /// Clients are counted across the members they are connected to.
+    /// The event is sent for the whole ensemble, so its host is the cluster name.
+    fn ensemble(&self, samples: &[(String, Clients)]) -> Event {
+        let mut by_ip: BTreeMap<String, Usage> = BTreeMap::new();
+        for (_, clients) in samples {
+            for (ip, usage) in &clients.by_ip {
+                let total = by_ip.entry(ip.clone()).or_default();
+                total.connections += usage.connections;
+                total.watches += usage.watches;
+            }
+        }
+
+        let over = by_ip.values().filter(|u| self.client_exceeds(u)).count();
+        let mut description = format!(
+            "clients: {}, over the limits: {over}, limits: {} connections",
+            by_ip.len(),
+            self.max_client_connections
+        );
+        if self.watches_per_client {
+            description = format!("{description}, {} watches", self.max_client_watches);
+        }
+        let busiest = by_ip.values().map(|u| u.connections).max().unwrap_or(0);
+
+        Event {
+            host: self.cluster.clone(),
+            service: String::from("clients"),
+            instance: String::from(""),
+            status: if over > 0 { Status::WARN } else { Status::OK },
+            description: self.describe(description, &by_ip),
+            tags: self.tags.clone(),
+            metrics: metrics(&[
+                ("clients", by_ip.len() as f64),
+                ("clients_over_limits", over as f64),
+                ("busiest_client_connections", busiest as f64),
+            ]),
+            cluster_level: true,
+        }
+    }
+
+    async fn fetch(&self, target: &str) -> Result<Clients, CollectError> {
+        let parse_error = |source| CollectError::Parse {
+            host: target.to_string(),
+            source,
+        };
+
+        let cons = four_letter_word(target, "cons").await?;
+        let sessions = timed_sync(Phase::Parse, || parse_cons(&cons)).map_err(parse_error)?;
+        let wchs = four_letter_word(target, "wchs").await?;
+        let watches = timed_sync(Phase::Parse, || parse_wchs(&wchs)).map_err(parse_error)?;
+        let per_session = if self.watches_per_client {
+            let wchc = four_letter_word(target, "wchc").await?;
+            timed_sync(Phase::Parse, || parse_wchc(&wchc)).map_err(parse_error)?
+        } else {
+            HashMap::new()
+        };
+
+        let mut clients = Clients {
+            total: Usage {
+                connections: sessions.len(),
+                watches,
+            },
+            by_ip: BTreeMap::new(),
+        };
+        for (ip, sid) in sessions {
+            let usage = clients.by_ip.entry(ip).or_default();
+            usage.connections += 1;
+            usage.watches += per_session.get(&sid).copied().unwrap_or(0);
+        }
+
+        Ok(clients)
+    }
+}
+
+#[async_trait]
+impl Check for ZooClientsCheck {
+    type Sample = Clients;
+
+    fn kind(&self) -> &'static str {
+        "zoo-clients"
+    }
+
+    fn cluster(&self) -> &str {
+        &self.cluster
+    }
+
+    async fn targets(&self) -> Vec<String> {
+        self.hosts.refresh().await
+    }
+
+    async fn collect(&self, target: &str) -> Result<Clients, BoxError> {
+        Ok(self.fetch(target).await?)
+    }
+
+    fn retry(&self, error: &BoxError, attempt: u32) -> bool {
+        attempt <= self.retries
+            && error
+                .downcast_ref::<CollectError>()
+                .is_some_and(CollectError::is_transient)
+    }
+
+    fn failed(&self, error: &BoxError) -> Option<Event> {
+        let error = error.downcast_ref::<CollectError>()?;
+
+        Some(Event {
+            host: error.host().to_string(),
+            service: String::from("clients"),
+            instance: String::from(""),
+            status: error.status(),
+            description: error.to_string(),
+            tags: self.tags.clone(),
+            metrics: BTreeMap::new(),
+            cluster_level: false,
+        })
+    }
+
+    fn evaluate(&self, samples: Vec<(String, Clients)>) -> Vec<Event> {
+        let mut events: Vec<Event> = samples
+            .iter()
+            .map(|(host, clients)| self.member(host, clients))
+            .collect();
+        events.push(self.ensemble(&samples));
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONS: &str = " /10.0.0.5:41234[1](queued=0,recved=120,sent=120,sid=0x1000,lop=PING)
+ /10.0.0.9:50010[1](queued=0,recved=10,sent=10,sid=0x1001,lop=GETD)
+ /10.0.0.9:50011[1](queued=0,recved=10,sent=10,sid=0x1002,lop=GETD)
+ /[fd00:0:0:0:0:0:0:7]:33000[1](queued=0,recved=4,sent=4,sid=0x1003,lop=PING)
+ /127.0.0.1:54016[0](queued=0,recved=1,sent=0)
+
+";
+
+    fn check(extra: &str) -> ZooClientsCheck {
+        let config: ZooConfig = toml::from_str(&format!(
+            r#"
+            cluster = "main"
+            hosts = ["zoo1", "zoo2"]
+
+            [clients]
+            max_client_connections = 2
+            max_client_watches = 10
+            max_member_connections = 3
+            max_member_watches = 20
+            top = 2
+            {extra}
+            "#
+        ))
+        .unwrap();
+
+        ZooClientsCheck::new(&config, config.clients.as_ref().unwrap()).unwrap()
+    }
+
+    fn clients(by_ip: &[(&str, usize, usize)], watches: usize) -> Clients {
+        let by_ip: BTreeMap<String, Usage> = by_ip
+            .iter()
+            .map(|(ip, connections, watches)| {
+                let usage = Usage {
+                    connections: *connections,
+                    watches: *watches,
+                };
+                (ip.to_string(), usage)
+            })
+            .collect();
+
+        Clients {
+            total: Usage {
+                connections: by_ip.values().map(|u| u.connections).sum(),
+                watches,
+            },
+            by_ip,
+        }
+    }
+
+    #[test]
+    fn cons_should_list_sessions_by_ip() {
+        let sessions = parse_cons(CONS).unwrap();
+
+        assert_eq!(
+            sessions,
+            [
+                ("10.0.0.5", "0x1000"),
+                ("10.0.0.9", "0x1001"),
+                ("10.0.0.9", "0x1002"),
+                ("fd00:0:0:0:0:0:0:7", "0x1003"),
+            ]
+            .map(|(ip, sid)| (ip.to_string(), sid.to_string()))
+        );
+    }
+
+    #[test]
+    fn unexpected_replies_should_not_parse() {
+        assert!(parse_cons("").is_err());
+        assert!(parse_cons("cons is not executed because it is not in the whitelist.").is_err());
+        assert!(parse_wchs("3 connections watching 2 paths\n").is_err());
+        assert!(parse_wchc("\t/app/config\n").is_err());
+    }
+
+    #[test]
+    fn watches_should_be_counted_per_session() {
+        assert_eq!(
+            parse_wchs("2 connections watching 3 paths\nTotal watches:4\n").unwrap(),
+            4
+        );
+
+        let watches =
+            parse_wchc("0x1000\n\t/app/config\n0x1001\n\t/app/config\n\t/app/lock\n\n").unwrap();
+        assert_eq!(watches["0x1000"], 1);
+        assert_eq!(watches["0x1001"], 2);
+    }
+
+    #[test]
+    fn busy_member_should_warn() {
+        let clients = clients(&[("10.0.0.5", 1, 1), ("10.0.0.9", 3, 25)], 26);
+
+        let event = check("").member("zoo1", &clients);
+
+        assert_eq!(event.status, Status::WARN);
+        assert_eq!(
+            event.description,
+            "connections: 4/3, watches: 26/20\n10.0.0.9: 3 connections, 25 watches\n10.0.0.5: 1 connections, 1 watches"
+        );
+    }
+
+    #[test]
+    fn clients_should_be_counted_across_members() {
+        let samples = vec![
+            (
+                String::from("zoo1"),
+                clients(&[("10.0.0.5", 1, 1), ("10.0.0.9", 1, 2)], 3),
+            ),
+            (
+                String::from("zoo2"),
+                clients(&[("10.0.0.7", 1, 0), ("10.0.0.9", 2, 2)], 2),
+            ),
+        ];
+
+        let events = check("").evaluate(samples);
+
+        assert!(events[..2].iter().all(|e| e.status == Status::OK));
+        let ensemble = &events[2];
+        assert_eq!(ensemble.host, "main");
+        assert_eq!(ensemble.status, Status::WARN);
+        assert_eq!(
+            ensemble.description,
+            "clients: 3, over the limits: 1, limits: 2 connections, 10 watches\n\
+             10.0.0.9: 3 connections, 4 watches\n\
+             10.0.0.5: 1 connections, 1 watches"
+        );
+    }
+
+    #[test]
+    fn watches_should_be_ignored_without_wchc() {
+        let check = check("watches_per_client = false");
+        let samples = vec![(String::from("zoo1"), clients(&[("10.0.0.9", 1, 50)], 50))];
+
+        let event = check.ensemble(&samples);
+
+        assert_eq!(event.status, Status::OK);
+        assert_eq!(
+            event.description,
+            "clients: 1, over the limits: 0, limits: 2 connections\n10.0.0.9: 1 connections"
+        );
+    }
+}
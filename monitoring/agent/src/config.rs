@@ -0,0 +1,813 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use reqwest::Url;
+use rmq::rmq::Tls;
+use serde::Deserialize;
+
+use crate::discovery::Discovery;
+use crate::enrich::Enrichment;
+use crate::juggler::Batching;
+use crate::naming::HostNaming;
+use crate::secret::{read_secret_file, Credentials, Secret};
+use crate::silence::Silence;
+
+#[derive(Deserialize, Debug)]
+pub struct Config {
+    /// Seconds between runs. The agent runs once and exits when unset.
+    pub interval: Option<u64>,
+    /// Reported by the `monitoring-self` event, a hash of the config file when unset.
+    pub version: Option<String>,
+    #[serde(default)]
+    pub self_monitoring: SelfMonitoringConfig,
+    pub juggler: JugglerConfig,
+    pub alertmanager: Option<AlertmanagerConfig>,
+    pub pagerduty: Option<PagerDutyConfig>,
+    /// Status page, served only when the agent runs with an `interval`.
+    pub status: Option<StatusConfig>,
+    /// Local history of every sent event.
+    pub history: Option<HistoryConfig>,
+    /// Time-series backends for the raw metric values of the events.
+    #[serde(default)]
+    pub metrics: Vec<MetricsConfig>,
+    /// Silences managed by the `silence` subcommand.
+    pub silence_file: Option<PathBuf>,
+    #[serde(default)]
+    pub silence: Vec<Silence>,
+    /// What the checks and sinks remember between runs, e.g. the last leader of every ensemble.
+    #[serde(default = "default_state_file")]
+    pub state_file: PathBuf,
+    /// Juggler host naming of every cluster without its own `naming`.
+    #[serde(default)]
+    pub naming: HostNaming,
+    /// Enrichment of every cluster of a check kind, keyed by the kind.
+    #[serde(default)]
+    pub enrichment: BTreeMap<String, Enrichment>,
+    #[serde(default)]
+    pub zoo: Vec<ZooConfig>,
+    #[serde(default)]
+    pub rmq: Vec<RmqConfig>,
+    #[serde(default)]
+    pub redis: Vec<RedisConfig>,
+    #[serde(default)]
+    pub etcd: Vec<EtcdConfig>,
+    #[serde(default)]
+    pub postgres: Vec<PostgresConfig>,
+    #[serde(default)]
+    pub tcp: Vec<TcpProbeConfig>,
+    #[serde(default)]
+    pub http: Vec<HttpProbeConfig>,
+}
+
+fn check_url(problems: &mut Vec<String>, what: &str, url: &str) {
+    match Url::parse(url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+        Ok(url) => problems.push(format!("{what}: unsupported scheme {}", url.scheme())),
+        Err(e) => problems.push(format!("{what}: invalid URL {url:?}: {e}")),
+    }
+}
+
+impl Config {
+    /// Problems parsing cannot catch, empty when the config is fine.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        check_url(&mut problems, "juggler.url", &self.juggler.url);
+        let batching = &self.juggler.batching;
+        if batching.max_events == 0 || batching.max_bytes == 0 || batching.concurrency == 0 {
+            problems.push(String::from(
+                "juggler: max_events, max_bytes and concurrency must be positive",
+            ));
+        }
+        for c in &self.zoo {
+            let Some(clients) = &c.clients else { continue };
+            if [
+                clients.max_client_connections,
+                clients.max_client_watches,
+                clients.max_member_connections,
+                clients.max_member_watches,
+                clients.top,
+            ]
+            .contains(&0)
+            {
+                problems.push(format!(
+                    "zoo/{}: clients limits and top must be positive",
+                    c.cluster
+                ));
+            }
+        }
+        if let Some(alertmanager) = &self.alertmanager {
+            check_url(&mut problems, "alertmanager.url", &alertmanager.url);
+        }
+        if let Some(pagerduty) = &self.pagerduty {
+            check_url(&mut problems, "pagerduty.url", &pagerduty.url);
+            if let Err(e) = pagerduty.routing_key() {
+                problems.push(format!("pagerduty: {e}"));
+            }
+        }
+        if let Some(url) = &self.self_monitoring.heartbeat_url {
+            check_url(&mut problems, "self_monitoring.heartbeat_url", url);
+        }
+        for metrics in &self.metrics {
+            match metrics.protocol {
+                Protocol::Influx => check_url(&mut problems, "metrics.address", &metrics.address),
+                _ if !metrics.address.contains(':') => problems.push(format!(
+                    "metrics.address: {:?} has no port",
+                    metrics.address
+                )),
+                _ => {}
+            }
+        }
+        for (kind, enrichment) in &self.enrichment {
+            if !KINDS.contains(&kind.as_str()) {
+                problems.push(format!("enrichment.{kind}: unknown check kind"));
+            }
+            if let Some(runbook) = &enrichment.runbook {
+                let what = format!("enrichment.{kind}.runbook");
+                check_url(&mut problems, &what, runbook);
+            }
+        }
+        for (kind, cluster, enrichment) in self.cluster_enrichments() {
+            if let Some(runbook) = &enrichment.runbook {
+                let what = format!("{kind}/{cluster}: runbook");
+                check_url(&mut problems, &what, runbook);
+            }
+        }
+        if self.interval == Some(0) {
+            problems.push(String::from("interval must be positive"));
+        }
+
+        // kind, name, listed hosts and the members the cluster is expected to have,
+        // discovered clusters have no hosts to count
+        let mut clusters: Vec<(&str, &str, Option<usize>, usize)> = Vec::new();
+        for c in &self.zoo {
+            let expected = c.expected_followers.map(|f| f + c.expected_observers + 1);
+            clusters.push((
+                "zoo",
+                &c.cluster,
+                listed(&c.hosts, &c.discovery),
+                expected.unwrap_or(0),
+            ));
+        }
+        for c in &self.rmq {
+            clusters.push(("rmq", &c.cluster, listed(&c.hosts, &c.discovery), 0));
+        }
+        for c in &self.redis {
+            let expected = c.expected_replicas.map_or(0, |r| r + 1);
+            clusters.push((
+                "redis",
+                &c.cluster,
+                listed(&c.hosts, &c.discovery),
+                expected,
+            ));
+            if c.memory_warn >= c.memory_crit {
+                let problem = format!("redis/{}: memory_warn is not below memory_crit", c.cluster);
+                problems.push(problem);
+            }
+        }
+        for c in &self.etcd {
+            clusters.push(("etcd", &c.cluster, listed(&c.hosts, &c.discovery), 0));
+        }
+        for c in &self.postgres {
+            let expected = c.expected_replicas.map_or(0, |r| r + 1);
+            clusters.push((
+                "postgres",
+                &c.cluster,
+                listed(&c.hosts, &c.discovery),
+                expected,
+            ));
+        }
+        for p in &self.tcp {
+            clusters.push(("tcp", &p.name, Some(p.hosts.len()), 0));
+        }
+        for p in &self.http {
+            clusters.push(("http", &p.name, Some(p.urls.len()), 0));
+            for url in &p.urls {
+                check_url(&mut problems, &format!("http/{}", p.name), url);
+            }
+        }
+
+        let mut seen = HashSet::new();
+        for (kind, cluster, hosts, expected) in clusters {
+            if hosts == Some(0) {
+                problems.push(format!("{kind}/{cluster}: no hosts"));
+            }
+            if let Some(hosts) = hosts.filter(|&hosts| expected > hosts) {
+                problems.push(format!(
+                    "{kind}/{cluster}: {expected} members expected but only {hosts} hosts are listed"
+                ));
+            }
+            if !seen.insert((kind, cluster)) {
+                problems.push(format!("{kind}/{cluster}: defined more than once"));
+            }
+        }
+
+        problems
+    }
+
+    fn cluster_enrichments(&self) -> Vec<(&str, &str, &Enrichment)> {
+        let zoo = self.zoo.iter().map(|c| ("zoo", &c.cluster, &c.enrichment));
+        let rmq = self.rmq.iter().map(|c| ("rmq", &c.cluster, &c.enrichment));
+        let redis = self
+            .redis
+            .iter()
+            .map(|c| ("redis", &c.cluster, &c.enrichment));
+        let etcd = self
+            .etcd
+            .iter()
+            .map(|c| ("etcd", &c.cluster, &c.enrichment));
+        let postgres = self
+            .postgres
+            .iter()
+            .map(|c| ("postgres", &c.cluster, &c.enrichment));
+        let tcp = self.tcp.iter().map(|p| ("tcp", &p.name, &p.enrichment));
+        let http = self.http.iter().map(|p| ("http", &p.name, &p.enrichment));
+
+        zoo.chain(rmq)
+            .chain(redis)
+            .chain(etcd)
+            .chain(postgres)
+            .chain(tcp)
+            .chain(http)
+            .filter_map(|(kind, name, enrichment)| {
+                Some((kind, name.as_str(), enrichment.as_ref()?))
+            })
+            .collect()
+    }
+}
+
+/// Kinds of the checks a config can register.
+pub const KINDS: [&str; 8] = [
+    "zoo",
+    "zoo-clients",
+    "rmq",
+    "redis",
+    "etcd",
+    "postgres",
+    "tcp",
+    "http",
+];
+
+fn listed(hosts: &[String], discovery: &Option<Discovery>) -> Option<usize> {
+    match discovery {
+        Some(_) => None,
+        None => Some(hosts.len()),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct JugglerConfig {
+    pub url: String,
+    #[serde(default = "default_source")]
+    pub source: String,
+    /// `max_events`, `max_bytes` and `concurrency` of the batches.
+    #[serde(flatten)]
+    pub batching: Batching,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AlertmanagerConfig {
+    pub url: String,
+    // firing alerts end after this long unless the next runs refresh them
+    #[serde(default = "default_alert_ttl_seconds")]
+    pub ttl_seconds: u64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PagerDutyConfig {
+    #[serde(default = "default_pagerduty_url")]
+    pub url: String,
+    pub routing_key: Option<Secret>,
+    pub routing_key_file: Option<PathBuf>,
+}
+
+impl PagerDutyConfig {
+    pub fn routing_key(&self) -> Result<Secret, String> {
+        match (&self.routing_key, &self.routing_key_file) {
+            (Some(key), _) => Ok(key.clone()),
+            (None, Some(path)) => read_secret_file(path),
+            (None, None) => Err(String::from("PagerDuty routing key is not set")),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct SelfMonitoringConfig {
+    /// Juggler host of the `monitoring-self` event, the Juggler source when unset.
+    pub host: Option<String>,
+    /// Pinged after every run whose events were all sent, so a silent agent becomes an alert.
+    pub heartbeat_url: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct StatusConfig {
+    #[serde(default = "default_status_listen")]
+    pub listen: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct HistoryConfig {
+    pub path: PathBuf,
+    #[serde(default = "default_retention_days")]
+    pub retention_days: u64,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    /// Plaintext protocol over TCP, `address` is `host:port`.
+    Graphite,
+    /// Gauges over UDP, `address` is `host:port`.
+    Statsd,
+    /// Line protocol over HTTP, `address` is the write URL.
+    Influx,
+}
+
+/// Where the raw metric values of the events go and how they are named.
+#[derive(Deserialize, Debug)]
+pub struct MetricsConfig {
+    pub protocol: Protocol,
+    pub address: String,
+    #[serde(default = "default_metrics_prefix")]
+    pub prefix: String,
+    /// Event fields (`host`, `service`, `instance`) to tag names.
+    /// Graphite and StatsD put the values of the mapped fields in the metric path instead.
+    #[serde(default = "default_metric_tags")]
+    pub tags: BTreeMap<String, String>,
+    /// Static tags added to every InfluxDB point.
+    #[serde(default)]
+    pub extra_tags: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ZooConfig {
+    pub cluster: String,
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    pub discovery: Option<Discovery>,
+    // every host but the leader and the observers by default
+    pub expected_followers: Option<usize>,
+    #[serde(default)]
+    pub expected_observers: usize,
+    #[serde(default = "default_zoo_tags")]
+    pub tags: Vec<String>,
+    // WARN when the leader changed more often within the window
+    #[serde(default = "default_max_leader_changes")]
+    pub max_leader_changes: usize,
+    #[serde(default = "default_leader_changes_window")]
+    pub leader_changes_window: u64,
+    // more attempts within a run after a timeout or a broken connection
+    #[serde(default = "default_zoo_retries")]
+    pub retries: u32,
+    /// Watches the connections and watches of clients, `[clients]` turns it on.
+    pub clients: Option<ZooClientsConfig>,
+    pub naming: Option<HostNaming>,
+    pub enrichment: Option<Enrichment>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ZooClientsConfig {
+    // per client IP, summed over the members it is connected to
+    #[serde(default = "default_max_client_connections")]
+    pub max_client_connections: usize,
+    #[serde(default = "default_max_client_watches")]
+    pub max_client_watches: usize,
+    #[serde(default = "default_max_member_connections")]
+    pub max_member_connections: usize,
+    #[serde(default = "default_max_member_watches")]
+    pub max_member_watches: usize,
+    // `wchc` is costly on members with many watches
+    #[serde(default = "default_true")]
+    pub watches_per_client: bool,
+    // clients named in the descriptions
+    #[serde(default = "default_top_clients")]
+    pub top: usize,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RmqConfig {
+    pub cluster: String,
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    pub discovery: Option<Discovery>,
+    #[serde(default = "default_scheme")]
+    pub scheme: String,
+    #[serde(default = "default_rmq_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub path_prefix: String,
+    /// `ca_bundle`, `client_cert`, `client_key` and `insecure`.
+    #[serde(flatten)]
+    pub tls: Tls,
+    #[serde(default = "default_true")]
+    pub federation: bool,
+    #[serde(default = "default_true")]
+    pub shovels: bool,
+    #[serde(default)]
+    pub credentials: Credentials,
+    pub naming: Option<HostNaming>,
+    pub enrichment: Option<Enrichment>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RedisConfig {
+    pub cluster: String,
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    pub discovery: Option<Discovery>,
+    // every host but the master by default
+    pub expected_replicas: Option<usize>,
+    // replication offset a replica is behind the master by
+    #[serde(default = "default_max_lag_bytes")]
+    pub max_lag_bytes: u64,
+    // master_last_io_seconds_ago of a replica, formerly `max_lag_seconds`
+    #[serde(default = "default_max_lag_seconds", alias = "max_lag_seconds")]
+    pub max_last_io_seconds: u64,
+    // used_memory / maxmemory ratios
+    #[serde(default = "default_memory_warn")]
+    pub memory_warn: f64,
+    #[serde(default = "default_memory_crit")]
+    pub memory_crit: f64,
+    /// `AUTH` password and optionally the ACL user as the login.
+    #[serde(default)]
+    pub credentials: Credentials,
+    pub naming: Option<HostNaming>,
+    pub enrichment: Option<Enrichment>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct EtcdConfig {
+    pub cluster: String,
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    pub discovery: Option<Discovery>,
+    #[serde(default = "default_scheme")]
+    pub scheme: String,
+    // etcd --quota-backend-bytes, 2GiB by default
+    #[serde(default = "default_quota_bytes")]
+    pub quota_bytes: u64,
+    // dbSize / quota_bytes ratio to warn at
+    #[serde(default = "default_db_warn")]
+    pub db_warn: f64,
+    pub naming: Option<HostNaming>,
+    pub enrichment: Option<Enrichment>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PostgresConfig {
+    pub cluster: String,
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    pub discovery: Option<Discovery>,
+    #[serde(default = "default_database")]
+    pub database: String,
+    // every host but the primary by default
+    pub expected_replicas: Option<usize>,
+    #[serde(default = "default_pg_max_lag_bytes")]
+    pub max_lag_bytes: i64,
+    #[serde(default = "default_pg_max_lag_seconds")]
+    pub max_lag_seconds: f64,
+    #[serde(default)]
+    pub credentials: Credentials,
+    pub naming: Option<HostNaming>,
+    pub enrichment: Option<Enrichment>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TcpProbeConfig {
+    /// Juggler service of the probe events.
+    pub name: String,
+    /// `host:port` list.
+    pub hosts: Vec<String>,
+    // written right after connecting
+    pub send: Option<String>,
+    // substring the first reply must contain
+    pub expect: Option<String>,
+    #[serde(default = "default_probe_timeout_ms")]
+    pub timeout_ms: u64,
+    pub naming: Option<HostNaming>,
+    pub enrichment: Option<Enrichment>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct HttpProbeConfig {
+    /// Juggler service of the probe events.
+    pub name: String,
+    pub urls: Vec<String>,
+    #[serde(default = "default_probe_status")]
+    pub status: u16,
+    // regex the body must match
+    pub body: Option<String>,
+    // `$.a.b[0]` style path that must exist in the JSON body
+    pub json_path: Option<String>,
+    // value found at `json_path` must be equal to this one
+    pub json_equals: Option<serde_json::Value>,
+    // WARN when the response takes longer
+    pub max_latency_ms: Option<u64>,
+    #[serde(default = "default_probe_timeout_ms")]
+    pub timeout_ms: u64,
+    #[serde(default)]
+    pub insecure: bool,
+    pub naming: Option<HostNaming>,
+    pub enrichment: Option<Enrichment>,
+}
+
+fn default_source() -> String {
+    String::from("agent")
+}
+
+fn default_state_file() -> PathBuf {
+    PathBuf::from("agent-state.json")
+}
+
+fn default_alert_ttl_seconds() -> u64 {
+    5 * 60
+}
+
+fn default_pagerduty_url() -> String {
+    String::from(crate::pagerduty::PAGERDUTY_URL)
+}
+
+fn default_status_listen() -> String {
+    String::from("0.0.0.0:8080")
+}
+
+fn default_retention_days() -> u64 {
+    30
+}
+
+fn default_metrics_prefix() -> String {
+    String::from("monitoring")
+}
+
+fn default_metric_tags() -> BTreeMap<String, String> {
+    BTreeMap::from([
+        (String::from("host"), String::from("host")),
+        (String::from("service"), String::from("service")),
+        (String::from("instance"), String::from("instance")),
+    ])
+}
+
+fn default_max_leader_changes() -> usize {
+    2
+}
+
+fn default_leader_changes_window() -> u64 {
+    60 * 60
+}
+
+fn default_zoo_retries() -> u32 {
+    1
+}
+
+fn default_max_client_connections() -> usize {
+    100
+}
+
+fn default_max_client_watches() -> usize {
+    10_000
+}
+
+fn default_max_member_connections() -> usize {
+    1000
+}
+
+fn default_max_member_watches() -> usize {
+    100_000
+}
+
+fn default_top_clients() -> usize {
+    3
+}
+
+fn default_zoo_tags() -> Vec<String> {
+    vec![
+        String::from("zoo"),
+        String::from("k8s"),
+        String::from("monitoring"),
+    ]
+}
+
+fn default_scheme() -> String {
+    String::from("http")
+}
+
+fn default_rmq_port() -> u16 {
+    15672
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_max_lag_bytes() -> u64 {
+    1024 * 1024
+}
+
+fn default_max_lag_seconds() -> u64 {
+    10
+}
+
+fn default_memory_warn() -> f64 {
+    0.8
+}
+
+fn default_quota_bytes() -> u64 {
+    2 * 1024 * 1024 * 1024
+}
+
+fn default_db_warn() -> f64 {
+    0.8
+}
+
+fn default_database() -> String {
+    String::from("postgres")
+}
+
+fn default_pg_max_lag_bytes() -> i64 {
+    16 * 1024 * 1024
+}
+
+fn default_pg_max_lag_seconds() -> f64 {
+    30.0
+}
+
+fn default_probe_timeout_ms() -> u64 {
+    2000
+}
+
+fn default_probe_status() -> u16 {
+    200
+}
+
+fn default_memory_crit() -> f64 {
+    0.95
+}
+
+pub fn load(path: &Path) -> Result<Config, String> {
+    let raw = fs::read_to_string(path)
+        .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+
+    let mut config: Config = toml::from_str(&raw)
+        .map_err(|e| format!("failed to parse {}: {}", path.display(), e.message()))?;
+
+    if config.version.is_none() {
+        config.version = Some(fingerprint(&raw));
+    }
+
+    Ok(config)
+}
+
+/// Short hash telling config files apart, stable for a given build of the agent.
+pub fn fingerprint(raw: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    raw.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn config_should_parse() {
+        let config: Config = toml::from_str(
+            r#"
+            interval = 60
+
+            [juggler]
+            url = "http://juggler.example.com"
+
+            [[zoo]]
+            cluster = "main"
+            hosts = ["zoo1.example.com", "zoo2.example.com", "zoo3.example.com"]
+
+            [[zoo]]
+            cluster = "billing"
+            hosts = ["10.0.0.4"]
+
+            [zoo.naming]
+            strategy = "alias"
+            aliases = { "10.0.0.4" = "zoo4" }
+
+            [[rmq]]
+            cluster = "federation"
+            hosts = ["rmq1.example.com"]
+            scheme = "https"
+            port = 15671
+            shovels = false
+
+            [rmq.credentials]
+            login = "monitoring"
+            password_file = "/var/run/secrets/rmq/password"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.interval, Some(60));
+        assert_eq!(config.juggler.source, "agent");
+        assert_eq!(config.zoo.len(), 2);
+        assert_eq!(config.zoo[1].cluster, "billing");
+        assert_eq!(config.naming, HostNaming::default());
+        assert_eq!(
+            config.zoo[1].naming,
+            Some(HostNaming::Alias {
+                aliases: HashMap::from([(String::from("10.0.0.4"), String::from("zoo4"))]),
+                fallback: String::from("{hostname}-test"),
+            })
+        );
+        assert_eq!(config.zoo[1].tags, vec!["zoo", "k8s", "monitoring"]);
+        assert_eq!(config.rmq[0].port, 15671);
+        assert!(config.rmq[0].federation);
+        assert!(!config.rmq[0].shovels);
+        assert_eq!(config.rmq[0].credentials.login.as_deref(), Some("monitoring"));
+    }
+
+    #[test]
+    fn config_version_should_default_to_fingerprint() {
+        let path = std::env::temp_dir().join(format!("agent-{}-config.toml", std::process::id()));
+        let raw = "[juggler]\nurl = \"http://juggler.example.com\"\n";
+
+        fs::write(&path, raw).unwrap();
+        assert_eq!(load(&path).unwrap().version, Some(fingerprint(raw)));
+        assert_ne!(
+            fingerprint(raw),
+            fingerprint("[juggler]\nurl = \"http://other\"\n")
+        );
+
+        fs::write(&path, format!("version = \"2024.1\"\n{raw}")).unwrap();
+        assert_eq!(load(&path).unwrap().version.as_deref(), Some("2024.1"));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn problems_should_be_reported() {
+        let config: Config = toml::from_str(
+            r#"
+            [juggler]
+            url = "juggler.example.com"
+            concurrency = 0
+
+            [[zoo]]
+            cluster = "main"
+            hosts = ["zoo1", "zoo2"]
+            expected_followers = 2
+
+            [[zoo]]
+            cluster = "main"
+            hosts = []
+
+            # members come from DNS, none have to be listed
+            [[zoo]]
+            cluster = "k8s"
+            expected_followers = 4
+            discovery = { name = "zookeeper.zoo.svc.cluster.local", port = 2181 }
+
+            [[metrics]]
+            protocol = "statsd"
+            address = "statsd.example.com"
+
+            [enrichment.zookeeper]
+            team = "storage"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.validate(),
+            [
+                "juggler.url: invalid URL \"juggler.example.com\": relative URL without a base",
+                "juggler: max_events, max_bytes and concurrency must be positive",
+                "metrics.address: \"statsd.example.com\" has no port",
+                "enrichment.zookeeper: unknown check kind",
+                "zoo/main: 3 members expected but only 2 hosts are listed",
+                "zoo/main: no hosts",
+                "zoo/main: defined more than once",
+            ]
+        );
+    }
+
+    #[test]
+    fn example_should_parse() {
+        let config: Config = toml::from_str(include_str!("../agent.example.toml")).unwrap();
+
+        assert!(config.silence_file.is_some());
+        assert_eq!(config.silence.len(), 1);
+        assert!(config.history.is_some());
+        assert_eq!(config.metrics.len(), 2);
+        assert!(config.zoo[1].clients.is_some());
+        assert_eq!(config.metrics[1].protocol, Protocol::Influx);
+        assert!(config.self_monitoring.heartbeat_url.is_some());
+        // secret files only exist where the agent is deployed
+        assert!(config
+            .validate()
+            .iter()
+            .all(|problem| problem.starts_with("pagerduty: failed to read secret")));
+    }
+}
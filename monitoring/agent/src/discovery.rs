@@ -0,0 +1,190 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+
+use hickory_resolver::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts};
+use hickory_resolver::TokioAsyncResolver;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::BoxError;
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Record {
+    /// A and AAAA records of a headless service, one per member.
+    #[default]
+    A,
+    /// SRV records, their targets and ports are the members.
+    Srv,
+}
+
+/// Where the members of a cluster are looked up in DNS on every run, the listed
+/// `hosts` of the cluster are kept until a lookup succeeds.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Discovery {
+    #[serde(default)]
+    pub record: Record,
+    pub name: String,
+    /// Added to the addresses of A/AAAA records, SRV records carry their own.
+    pub port: Option<u16>,
+    /// `ip:port` of the DNS server, the system resolver when unset.
+    pub resolver: Option<SocketAddr>,
+}
+
+fn resolver(discovery: &Discovery) -> Result<TokioAsyncResolver, BoxError> {
+    let Some(addr) = discovery.resolver else {
+        return Ok(TokioAsyncResolver::tokio_from_system_conf()?);
+    };
+
+    let servers = NameServerConfigGroup::from_ips_clear(&[addr.ip()], addr.port(), true);
+    let config = ResolverConfig::from_parts(None, vec![], servers);
+
+    Ok(TokioAsyncResolver::tokio(config, ResolverOpts::default()))
+}
+
+fn with_port(ip: IpAddr, port: Option<u16>) -> String {
+    match port {
+        Some(port) => SocketAddr::new(ip, port).to_string(),
+        None => ip.to_string(),
+    }
+}
+
+async fn lookup(
+    discovery: &Discovery,
+    resolver: &TokioAsyncResolver,
+) -> Result<Vec<String>, BoxError> {
+    // a trailing dot keeps search domains out of it
+    let name = format!("{}.", discovery.name.trim_end_matches('.'));
+
+    let mut hosts: Vec<String> = match discovery.record {
+        Record::A => resolver
+            .lookup_ip(name)
+            .await?
+            .iter()
+            .map(|ip| with_port(ip, discovery.port))
+            .collect(),
+        Record::Srv => resolver
+            .srv_lookup(name)
+            .await?
+            .iter()
+            .map(|srv| {
+                let target = srv.target().to_utf8();
+                format!("{}:{}", target.trim_end_matches('.'), srv.port())
+            })
+            .collect(),
+    };
+
+    hosts.sort();
+    hosts.dedup();
+    Ok(hosts)
+}
+
+/// Members of a cluster, either listed in the config or looked up in DNS on every run.
+pub struct Hosts {
+    // `kind/cluster`, for the logs
+    label: String,
+    discovery: Option<(Discovery, TokioAsyncResolver)>,
+    current: Mutex<Vec<String>>,
+}
+
+impl Hosts {
+    pub fn new(
+        label: String,
+        configured: Vec<String>,
+        discovery: Option<&Discovery>,
+    ) -> Result<Self, BoxError> {
+        let discovery = match discovery {
+            Some(d) => Some((d.clone(), resolver(d).map_err(|e| format!("{label}: {e}"))?)),
+            None => None,
+        };
+
+        Ok(Hosts {
+            label,
+            discovery,
+            current: Mutex::new(configured),
+        })
+    }
+
+    /// Hosts of the latest run.
+    pub fn current(&self) -> Vec<String> {
+        self.current.lock().unwrap().clone()
+    }
+
+    /// Number of hosts of the latest run, expected member counts default to it.
+    pub fn count(&self) -> usize {
+        self.current.lock().unwrap().len()
+    }
+
+    /// Looks the members up again. The previous list, initially the configured
+    /// hosts, is kept when the lookup fails.
+    pub async fn refresh(&self) -> Vec<String> {
+        let Some((discovery, resolver)) = &self.discovery else {
+            return self.current();
+        };
+
+        let found = match lookup(discovery, resolver).await {
+            Ok(found) if !found.is_empty() => found,
+            Ok(_) => {
+                warn!(
+                    "{}: {} has no records, keeping the previous hosts",
+                    self.label, discovery.name
+                );
+                return self.current();
+            }
+            Err(e) => {
+                warn!(
+                    "{}: failed to look up {}: {e}, keeping the previous hosts",
+                    self.label, discovery.name
+                );
+                return self.current();
+            }
+        };
+
+        let mut current = self.current.lock().unwrap();
+        if *current != found {
+            let added: Vec<&String> = found.iter().filter(|h| !current.contains(h)).collect();
+            let removed: Vec<&String> = current.iter().filter(|h| !found.contains(h)).collect();
+            info!(
+                "{}: hosts of {} changed, added: {added:?}, removed: {removed:?}",
+                self.label, discovery.name
+            );
+            *current = found;
+        }
+
+        current.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ports_should_be_added_to_addresses() {
+        assert_eq!(
+            with_port("10.0.0.1".parse().unwrap(), Some(2181)),
+            "10.0.0.1:2181"
+        );
+        assert_eq!(
+            with_port("fd00::1".parse().unwrap(), Some(2181)),
+            "[fd00::1]:2181"
+        );
+        assert_eq!(with_port("10.0.0.1".parse().unwrap(), None), "10.0.0.1");
+    }
+
+    #[test]
+    fn discovery_should_parse() {
+        let discovery: Discovery = toml::from_str(
+            r#"
+            record = "srv"
+            name = "_client._tcp.zookeeper.zoo.svc.cluster.local"
+            resolver = "10.96.0.10:53"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(discovery.record, Record::Srv);
+        assert_eq!(discovery.port, None);
+        assert_eq!(discovery.resolver, Some("10.96.0.10:53".parse().unwrap()));
+    }
+}
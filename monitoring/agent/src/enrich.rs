@@ -0,0 +1,256 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use regex::{Captures, Regex};
+use serde::Deserialize;
+
+use crate::check::{DynCheck, Report};
+use crate::juggler::Event;
+
+/// What on-call needs next to an event: where to look, who owns it and what
+/// the status was computed from. Set per check kind in `[enrichment.<kind>]`
+/// and per cluster, cluster settings win field by field.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Enrichment {
+    pub runbook: Option<String>,
+    pub team: Option<String>,
+    pub environment: Option<String>,
+    /// Lists the raw metric values of the event in its description.
+    pub metrics: Option<bool>,
+    /// Description templates keyed by service. `{description}` is the computed one,
+    /// `{host}`, `{service}`, `{instance}`, `{status}`, `{cluster}`, `{team}`,
+    /// `{environment}`, `{runbook}` and any metric name of the event are filled in.
+    #[serde(default)]
+    pub descriptions: BTreeMap<String, String>,
+}
+
+impl Enrichment {
+    /// `self` with the fields set in `cluster` replaced.
+    pub fn merge(&self, cluster: &Enrichment) -> Enrichment {
+        let mut descriptions = self.descriptions.clone();
+        descriptions.extend(cluster.descriptions.clone());
+
+        Enrichment {
+            runbook: cluster.runbook.clone().or_else(|| self.runbook.clone()),
+            team: cluster.team.clone().or_else(|| self.team.clone()),
+            environment: cluster
+                .environment
+                .clone()
+                .or_else(|| self.environment.clone()),
+            metrics: cluster.metrics.or(self.metrics),
+            descriptions,
+        }
+    }
+}
+
+/// Fills a template, unknown placeholders are left as they are.
+fn fill(placeholder: &Regex, template: &str, values: &BTreeMap<&str, String>) -> String {
+    placeholder
+        .replace_all(template, |caps: &Captures| match values.get(&caps[1]) {
+            Some(value) => value.clone(),
+            None => caps[0].to_string(),
+        })
+        .into_owned()
+}
+
+/// Applies the enrichment of one cluster to its events.
+pub struct Enricher {
+    enrichment: Enrichment,
+    cluster: String,
+    // `{name}` placeholders, metric names may contain dots
+    placeholder: Regex,
+}
+
+impl Enricher {
+    pub fn new(enrichment: Enrichment, cluster: &str) -> Self {
+        Self {
+            enrichment,
+            cluster: cluster.to_string(),
+            placeholder: Regex::new(r"\{([A-Za-z0-9_.\-]+)\}").unwrap(),
+        }
+    }
+
+    pub fn enrich(&self, event: &mut Event) {
+        let e = &self.enrichment;
+
+        let mut values: BTreeMap<&str, String> = event
+            .metrics
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.to_string()))
+            .collect();
+        values.insert("description", event.description.clone());
+        values.insert("host", event.host.clone());
+        values.insert("service", event.service.clone());
+        values.insert("instance", event.instance.clone());
+        values.insert("status", format!("{:?}", event.status));
+        values.insert("cluster", self.cluster.clone());
+        for (key, value) in [
+            ("team", &e.team),
+            ("environment", &e.environment),
+            ("runbook", &e.runbook),
+        ] {
+            if let Some(value) = value {
+                values.insert(key, value.clone());
+            }
+        }
+
+        let mut description = match e.descriptions.get(&event.service) {
+            Some(template) => fill(&self.placeholder, template, &values),
+            None => event.description.clone(),
+        };
+
+        let mut about = vec![format!("cluster: {}", self.cluster)];
+        if let Some(team) = &e.team {
+            about.push(format!("team: {team}"));
+        }
+        if let Some(environment) = &e.environment {
+            about.push(format!("environment: {environment}"));
+        }
+        description.push('\n');
+        description.push_str(&about.join(", "));
+
+        if let Some(runbook) = &e.runbook {
+            description.push_str(&format!("\nrunbook: {runbook}"));
+        }
+        if e.metrics == Some(true) && !event.metrics.is_empty() {
+            let metrics: Vec<String> = event
+                .metrics
+                .iter()
+                .map(|(name, value)| format!("{name}={value}"))
+                .collect();
+            description.push_str(&format!("\nmetrics: {}", metrics.join(", ")));
+        }
+        event.description = description;
+
+        event.tags.push(format!("cluster:{}", self.cluster));
+        if let Some(team) = &e.team {
+            event.tags.push(format!("team:{team}"));
+        }
+        if let Some(environment) = &e.environment {
+            event.tags.push(format!("env:{environment}"));
+        }
+    }
+}
+
+/// A check whose events carry its cluster's enrichment.
+pub struct Enriched {
+    check: Box<dyn DynCheck>,
+    enricher: Enricher,
+}
+
+impl Enriched {
+    pub fn new(check: Box<dyn DynCheck>, enricher: Enricher) -> Self {
+        Self { check, enricher }
+    }
+}
+
+#[async_trait]
+impl DynCheck for Enriched {
+    fn kind(&self) -> &'static str {
+        self.check.kind()
+    }
+
+    fn cluster(&self) -> &str {
+        self.check.cluster()
+    }
+
+    async fn run(&self) -> Report {
+        let mut report = self.check.run().await;
+
+        for event in &mut report.events {
+            self.enricher.enrich(event);
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::juggler::{metrics, Status};
+
+    fn event() -> Event {
+        Event {
+            host: String::from("zoo1-test"),
+            service: String::from("state"),
+            instance: String::from(""),
+            status: Status::WARN,
+            description: String::from("leader. followers: 1/2"),
+            tags: vec![String::from("zoo")],
+            metrics: metrics(&[("zk_synced_followers", 1.0), ("zk_avg_latency", 0.5)]),
+            cluster_level: false,
+        }
+    }
+
+    #[test]
+    fn cluster_settings_should_win() {
+        let check: Enrichment = toml::from_str(
+            r#"
+            runbook = "https://wiki.example.com/zookeeper"
+            team = "storage"
+            descriptions = { state = "{description}", leader-election = "{description}" }
+            "#,
+        )
+        .unwrap();
+        let cluster: Enrichment = toml::from_str(
+            r#"
+            team = "billing"
+            environment = "production"
+            descriptions = { state = "{status}: {description}" }
+            "#,
+        )
+        .unwrap();
+
+        let merged = check.merge(&cluster);
+
+        assert_eq!(
+            merged.runbook.as_deref(),
+            Some("https://wiki.example.com/zookeeper")
+        );
+        assert_eq!(merged.team.as_deref(), Some("billing"));
+        assert_eq!(merged.environment.as_deref(), Some("production"));
+        assert_eq!(merged.descriptions["state"], "{status}: {description}");
+        assert_eq!(merged.descriptions["leader-election"], "{description}");
+    }
+
+    #[test]
+    fn events_should_be_enriched() {
+        let enrichment: Enrichment = toml::from_str(
+            r#"
+            runbook = "https://wiki.example.com/zookeeper"
+            team = "storage"
+            environment = "production"
+            metrics = true
+            descriptions = { state = "{description}, {zk_avg_latency}ms avg latency {unknown}" }
+            "#,
+        )
+        .unwrap();
+        let mut event = event();
+
+        Enricher::new(enrichment, "main").enrich(&mut event);
+
+        assert_eq!(
+            event.description,
+            "\
+leader. followers: 1/2, 0.5ms avg latency {unknown}
+cluster: main, team: storage, environment: production
+runbook: https://wiki.example.com/zookeeper
+metrics: zk_avg_latency=0.5, zk_synced_followers=1"
+        );
+        assert_eq!(
+            event.tags,
+            ["zoo", "cluster:main", "team:storage", "env:production"]
+        );
+    }
+
+    #[test]
+    fn services_without_template_should_keep_their_description() {
+        let mut event = event();
+
+        Enricher::new(Enrichment::default(), "main").enrich(&mut event);
+
+        assert_eq!(event.description, "leader. followers: 1/2\ncluster: main");
+        assert_eq!(event.tags, ["zoo", "cluster:main"]);
+    }
+}
@@ -0,0 +1,374 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::FromRow;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::juggler::Event;
+use crate::silence;
+use crate::sink::Sink;
+use crate::BoxError;
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS events (
+    timestamp INTEGER NOT NULL,
+    host TEXT NOT NULL,
+    service TEXT NOT NULL,
+    instance TEXT NOT NULL,
+    status TEXT NOT NULL,
+    description TEXT NOT NULL,
+    metrics TEXT NOT NULL,
+    silenced INTEGER NOT NULL DEFAULT 0
+)";
+
+// histories written before events were recorded with their silences
+const MIGRATION: &str = "ALTER TABLE events ADD COLUMN silenced INTEGER NOT NULL DEFAULT 0";
+
+const INSERT: &str = "INSERT INTO events
+    (timestamp, host, service, instance, status, description, metrics, silenced)
+    VALUES (?, ?, ?, ?, ?, ?, ?, ?)";
+
+const INDEX: &str =
+    "CREATE INDEX IF NOT EXISTS events_by_check ON events (host, service, instance, timestamp)";
+
+/// Every event the agent has collected, kept for `retention`.
+/// Silenced events are kept as they were collected and flagged as `silenced`.
+pub struct History {
+    pool: SqlitePool,
+    retention: Duration,
+}
+
+/// One stored event.
+#[derive(FromRow, Debug, Clone, PartialEq)]
+pub struct Record {
+    /// Unix time.
+    pub timestamp: i64,
+    pub host: String,
+    pub service: String,
+    pub instance: String,
+    pub status: String,
+    pub description: String,
+    /// JSON object of the raw metric values.
+    pub metrics: String,
+    /// A silence matched the event, so alerting sinks got it downgraded or not at all.
+    pub silenced: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct Filter {
+    pub host: Option<String>,
+    pub service: Option<String>,
+    /// Unix time, inclusive.
+    pub since: Option<i64>,
+    /// Unix time, exclusive.
+    pub until: Option<i64>,
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+impl History {
+    pub async fn open(path: &Path, retention: Duration) -> Result<Self, BoxError> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .map_err(|e| format!("failed to open {}: {e}", path.display()))?;
+
+        sqlx::query(SCHEMA).execute(&pool).await?;
+        sqlx::query(INDEX).execute(&pool).await?;
+
+        let columns: Vec<(String,)> =
+            sqlx::query_as("SELECT name FROM pragma_table_info('events')")
+                .fetch_all(&pool)
+                .await?;
+        if !columns.iter().any(|(name,)| name == "silenced") {
+            sqlx::query(MIGRATION).execute(&pool).await?;
+        }
+
+        Ok(History { pool, retention })
+    }
+
+    /// Stores the events of one run and drops the ones past retention.
+    pub async fn record(&self, events: &[Event]) -> Result<(), BoxError> {
+        self.record_at(now(), events).await
+    }
+
+    pub async fn record_at(&self, timestamp: i64, events: &[Event]) -> Result<(), BoxError> {
+        let mut tx = self.pool.begin().await?;
+
+        for event in events {
+            sqlx::query(INSERT)
+                .bind(timestamp)
+                .bind(&event.host)
+                .bind(&event.service)
+                .bind(&event.instance)
+                .bind(format!("{:?}", event.status))
+                .bind(&event.description)
+                .bind(serde_json::to_string(&event.metrics)?)
+                .bind(silence::is_silenced(event))
+                .execute(&mut tx)
+                .await?;
+        }
+
+        sqlx::query("DELETE FROM events WHERE timestamp < ?")
+            .bind(timestamp - self.retention.as_secs() as i64)
+            .execute(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn query(&self, filter: &Filter) -> Result<Vec<Record>, BoxError> {
+        Ok(sqlx::query_as(
+            "SELECT * FROM events
+             WHERE (?1 IS NULL OR host = ?1)
+               AND (?2 IS NULL OR service = ?2)
+               AND (?3 IS NULL OR timestamp >= ?3)
+               AND (?4 IS NULL OR timestamp < ?4)
+             ORDER BY timestamp, host, service, instance",
+        )
+        .bind(&filter.host)
+        .bind(&filter.service)
+        .bind(filter.since)
+        .bind(filter.until)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+}
+
+/// Parses `--since`/`--until`: RFC 3339, unix time or an age like `90s`, `15m`, `2h`, `7d`.
+pub fn parse_time(value: &str) -> Result<i64, String> {
+    if let Ok(unix) = value.parse::<i64>() {
+        return Ok(unix);
+    }
+
+    if let Ok(at) = OffsetDateTime::parse(value, &Rfc3339) {
+        return Ok(at.unix_timestamp());
+    }
+
+    let age = parse_duration(value).map_err(|_| format!("invalid time: {value}"))?;
+
+    Ok(now() - age.as_secs() as i64)
+}
+
+/// Parses a duration like `90s`, `15m`, `2h`, `7d`.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let unit = match value.chars().last() {
+        Some('s') => 1,
+        Some('m') => 60,
+        Some('h') => 60 * 60,
+        Some('d') => 24 * 60 * 60,
+        _ => return Err(format!("invalid duration: {value}")),
+    };
+    let amount: u64 = value[..value.len() - 1]
+        .parse()
+        .map_err(|_| format!("invalid duration: {value}"))?;
+
+    Ok(Duration::from_secs(amount * unit))
+}
+
+pub fn format_time(unix: i64) -> String {
+    OffsetDateTime::from_unix_timestamp(unix)
+        .ok()
+        .and_then(|at| at.format(&Rfc3339).ok())
+        .unwrap_or_else(|| unix.to_string())
+}
+
+/// Keeps the first record of every check and the ones where its status changed.
+pub fn transitions(records: Vec<Record>) -> Vec<Record> {
+    let mut last: HashMap<(String, String, String), String> = HashMap::new();
+
+    records
+        .into_iter()
+        .filter(|r| {
+            let key = (r.host.clone(), r.service.clone(), r.instance.clone());
+            last.insert(key, r.status.clone()).as_ref() != Some(&r.status)
+        })
+        .collect()
+}
+
+#[async_trait]
+impl Sink for History {
+    fn name(&self) -> &str {
+        "history"
+    }
+
+    fn records(&self) -> bool {
+        true
+    }
+
+    async fn send(&self, events: &[Event]) -> Result<(), BoxError> {
+        self.record(events).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::juggler::{metrics, Status};
+
+    fn path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("agent-{}-{name}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn event(host: &str, status: Status) -> Event {
+        Event {
+            host: host.to_string(),
+            service: String::from("state"),
+            instance: String::from(""),
+            status,
+            description: format!("{status:?}"),
+            tags: vec![],
+            metrics: metrics(&[("zk_znode_count", 5.0)]),
+            cluster_level: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn old_events_should_be_dropped() {
+        let path = path("retention");
+        let history = History::open(&path, Duration::from_secs(100)).await.unwrap();
+
+        history.record_at(1000, &[event("zoo1", Status::OK)]).await.unwrap();
+        history.record_at(1050, &[event("zoo1", Status::WARN)]).await.unwrap();
+        history.record_at(1101, &[event("zoo2", Status::OK)]).await.unwrap();
+
+        let records = history.query(&Filter::default()).await.unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].timestamp, 1050);
+        assert_eq!(records[0].metrics, r#"{"zk_znode_count":5.0}"#);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn silenced_events_should_be_flagged() {
+        let path = path("silenced");
+        let history = History::open(&path, Duration::from_secs(3600)).await.unwrap();
+
+        let mut silenced = event("zoo2", Status::CRIT);
+        silenced.tags.push(String::from(silence::SILENCED));
+        history
+            .record_at(10, &[event("zoo1", Status::OK), silenced])
+            .await
+            .unwrap();
+
+        let records = history.query(&Filter::default()).await.unwrap();
+        assert!(!records[0].silenced);
+        assert!(records[1].silenced);
+        assert_eq!(records[1].status, "CRIT");
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn histories_without_silences_should_be_migrated() {
+        let path = path("migration");
+        let old = SCHEMA.replace(",\n    silenced INTEGER NOT NULL DEFAULT 0", "");
+        let options = SqliteConnectOptions::new()
+            .filename(&path)
+            .create_if_missing(true);
+        let pool = SqlitePool::connect_with(options).await.unwrap();
+        sqlx::query(&old).execute(&pool).await.unwrap();
+        sqlx::query("INSERT INTO events VALUES (5, 'zoo1', 'state', '', 'OK', '', '{}')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool.close().await;
+
+        let history = History::open(&path, Duration::from_secs(3600)).await.unwrap();
+        history.record_at(10, &[event("zoo1", Status::OK)]).await.unwrap();
+
+        let records = history.query(&Filter::default()).await.unwrap();
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().all(|r| !r.silenced));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn query_should_filter() {
+        let path = path("filter");
+        let history = History::open(&path, Duration::from_secs(3600)).await.unwrap();
+
+        for (at, host) in [(10, "zoo1"), (20, "zoo2"), (30, "zoo1")] {
+            history.record_at(at, &[event(host, Status::OK)]).await.unwrap();
+        }
+
+        let filter = Filter {
+            host: Some(String::from("zoo1")),
+            since: Some(10),
+            until: Some(30),
+            ..Default::default()
+        };
+        let records = history.query(&filter).await.unwrap();
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].timestamp, 10);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn times_should_parse() {
+        assert_eq!(parse_time("1700000000"), Ok(1700000000));
+        assert_eq!(parse_time("2023-11-14T22:13:20Z"), Ok(1700000000));
+        assert!((now() - parse_time("2h").unwrap() - 7200).abs() <= 1);
+        assert!(parse_time("yesterday").is_err());
+        assert_eq!(format_time(1700000000), "2023-11-14T22:13:20Z");
+    }
+
+    #[test]
+    fn only_transitions_should_be_kept() {
+        let record = |timestamp, host: &str, status: &str| Record {
+            timestamp,
+            host: host.to_string(),
+            service: String::from("state"),
+            instance: String::new(),
+            status: status.to_string(),
+            description: String::new(),
+            metrics: String::from("{}"),
+            silenced: false,
+        };
+
+        let records = vec![
+            record(1, "zoo1", "OK"),
+            record(1, "zoo2", "OK"),
+            record(2, "zoo1", "OK"),
+            record(2, "zoo2", "CRIT"),
+            record(3, "zoo1", "OK"),
+            record(3, "zoo2", "OK"),
+        ];
+
+        let kept: Vec<(i64, String)> = transitions(records)
+            .into_iter()
+            .map(|r| (r.timestamp, r.host))
+            .collect();
+
+        assert_eq!(
+            kept,
+            vec![
+                (1, String::from("zoo1")),
+                (1, String::from("zoo2")),
+                (2, String::from("zoo2")),
+                (3, String::from("zoo2")),
+            ]
+        );
+    }
+}
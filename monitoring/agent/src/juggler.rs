@@ -0,0 +1,291 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use reqwest::{header::CONTENT_TYPE, Client};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::sink::Sink;
+use crate::BoxError;
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    OK,
+    WARN,
+    CRIT,
+}
+
+impl Status {
+    /// Severity name used by Alertmanager and PagerDuty.
+    pub fn severity(&self) -> &'static str {
+        match self {
+            Status::OK => "info",
+            Status::WARN => "warning",
+            Status::CRIT => "critical",
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Event {
+    pub host: String,
+    pub service: String,
+    pub instance: String,
+    pub status: Status,
+    pub description: String,
+    pub tags: Vec<String>,
+    /// Raw values the status was computed from, kept locally.
+    #[serde(skip)]
+    pub metrics: BTreeMap<String, f64>,
+    /// About the whole cluster rather than one member, its host is the cluster
+    /// name and no naming strategy applies to it.
+    #[serde(skip)]
+    pub cluster_level: bool,
+}
+
+#[derive(Serialize)]
+pub struct Payload<'a> {
+    pub source: &'a str,
+    pub events: &'a [Event],
+}
+
+pub fn metrics(values: &[(&str, f64)]) -> BTreeMap<String, f64> {
+    values.iter().map(|(k, v)| (k.to_string(), *v)).collect()
+}
+
+pub fn jugglerify(fqdn: &str) -> String {
+    format!("{}/events", fqdn)
+}
+
+/// Limits of one request to Juggler, larger runs are split into batches.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Batching {
+    #[serde(default = "default_max_events")]
+    pub max_events: usize,
+    /// Size of the JSON body. An event larger than this is still sent, on its own.
+    #[serde(default = "default_max_bytes")]
+    pub max_bytes: usize,
+    /// Batches in flight at once.
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+}
+
+impl Default for Batching {
+    fn default() -> Self {
+        Batching {
+            max_events: default_max_events(),
+            max_bytes: default_max_bytes(),
+            concurrency: default_concurrency(),
+        }
+    }
+}
+
+fn default_max_events() -> usize {
+    500
+}
+
+fn default_max_bytes() -> usize {
+    1024 * 1024
+}
+
+fn default_concurrency() -> usize {
+    4
+}
+
+/// Splits events into ranges within the limits, keeping their order.
+fn batches(source: &str, events: &[Event], limits: &Batching) -> Vec<Range<usize>> {
+    // `{"source":"...","events":[]}`
+    let empty = Payload {
+        source,
+        events: &[],
+    };
+    let envelope = serde_json::to_vec(&empty).map_or(0, |body| body.len());
+
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    let mut bytes = envelope;
+    for (i, event) in events.iter().enumerate() {
+        let size = serde_json::to_vec(event).map_or(0, |e| e.len());
+        // every event after the first adds a comma
+        let added = if i == start { size } else { size + 1 };
+
+        if i > start && (i - start == limits.max_events || bytes + added > limits.max_bytes) {
+            ranges.push(start..i);
+            start = i;
+            bytes = envelope + size;
+        } else {
+            bytes += added;
+        }
+    }
+    if start < events.len() {
+        ranges.push(start..events.len());
+    }
+
+    ranges
+}
+
+/// Outcome of one request of a batched send.
+#[derive(Debug)]
+pub struct BatchResult {
+    /// Position of the batch, starting at 1.
+    pub batch: usize,
+    /// Events of the batch within the sent ones.
+    pub events: Range<usize>,
+    pub result: Result<serde_json::Value, reqwest::Error>,
+}
+
+/// Juggler client shared by every check.
+pub struct Juggler {
+    client: Client,
+    url: String,
+    source: String,
+    batching: Batching,
+}
+
+impl Juggler {
+    pub fn new(client: Client, url: String, source: String) -> Self {
+        Juggler {
+            client,
+            url,
+            source,
+            batching: Batching::default(),
+        }
+    }
+
+    pub fn with_batching(mut self, batching: Batching) -> Self {
+        self.batching = batching;
+        self
+    }
+
+    /// Sends the events in batches within the limits, `concurrency` of them at once.
+    /// Results are in batch order.
+    pub async fn send_batches(&self, events: &[Event]) -> Vec<BatchResult> {
+        let ranges = batches(&self.source, events, &self.batching);
+        let total = ranges.len();
+
+        stream::iter(ranges.into_iter().enumerate())
+            .map(|(i, range)| async move {
+                let result = self.send(&events[range.clone()]).await;
+                match &result {
+                    Ok(reply) => debug!("Juggler reply to batch {}/{total}: {reply}", i + 1),
+                    Err(e) => warn!("batch {}/{total} failed: {e}", i + 1),
+                }
+
+                BatchResult {
+                    batch: i + 1,
+                    events: range,
+                    result,
+                }
+            })
+            .buffered(self.batching.concurrency.max(1))
+            .collect()
+            .await
+    }
+
+    /// Sends the events in one request, whatever their number.
+    pub async fn send(&self, events: &[Event]) -> Result<serde_json::Value, reqwest::Error> {
+        let payload = Payload {
+            source: &self.source,
+            events,
+        };
+
+        self.client
+            .post(jugglerify(&self.url))
+            .header(CONTENT_TYPE, "application/json")
+            .json(&payload)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+    }
+}
+
+#[async_trait]
+impl Sink for Juggler {
+    fn name(&self) -> &str {
+        "juggler"
+    }
+
+    async fn send(&self, events: &[Event]) -> Result<(), BoxError> {
+        let results = self.send_batches(events).await;
+        let total = results.len();
+
+        let failed: Vec<String> = results
+            .iter()
+            .filter_map(|r| {
+                let e = r.result.as_ref().err()?;
+                Some(format!(
+                    "batch {}/{total} (events {}..{}): {e}",
+                    r.batch, r.events.start, r.events.end
+                ))
+            })
+            .collect();
+
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(failed.join("; ").into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(n: usize) -> Vec<Event> {
+        (0..n)
+            .map(|i| Event {
+                host: format!("zoo{i}-test"),
+                service: String::from("state"),
+                instance: String::from(""),
+                status: Status::OK,
+                description: String::from("follower"),
+                tags: vec![],
+                metrics: BTreeMap::new(),
+                cluster_level: false,
+            })
+            .collect()
+    }
+
+    fn limits(max_events: usize, max_bytes: usize) -> Batching {
+        Batching {
+            max_events,
+            max_bytes,
+            concurrency: 1,
+        }
+    }
+
+    #[test]
+    fn batches_should_hold_at_most_max_events() {
+        let ranges = batches("test", &events(5), &limits(2, usize::MAX));
+        assert_eq!(ranges, [0..2, 2..4, 4..5]);
+
+        assert!(batches("test", &[], &limits(2, usize::MAX)).is_empty());
+    }
+
+    #[test]
+    fn batches_should_fit_in_max_bytes() {
+        let events = events(3);
+        let body = |events: &[Event]| {
+            serde_json::to_vec(&Payload {
+                source: "test",
+                events,
+            })
+            .unwrap()
+            .len()
+        };
+
+        // exactly two events fit
+        let ranges = batches("test", &events, &limits(100, body(&events[..2])));
+        assert_eq!(ranges, [0..2, 2..3]);
+
+        // too small for any event, each one goes on its own
+        let ranges = batches("test", &events, &limits(100, 10));
+        assert_eq!(ranges, [0..1, 1..2, 2..3]);
+    }
+}
@@ -0,0 +1,22 @@
+pub mod agent;
+pub mod alertmanager;
+pub mod check;
+pub mod checks;
+pub mod config;
+pub mod discovery;
+pub mod enrich;
+pub mod history;
+pub mod juggler;
+pub mod logging;
+pub mod meta;
+pub mod metrics;
+pub mod naming;
+pub mod pagerduty;
+pub mod preflight;
+pub mod secret;
+pub mod silence;
+pub mod sink;
+pub mod state;
+pub mod status;
+
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
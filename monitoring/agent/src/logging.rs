@@ -0,0 +1,120 @@
+use std::cell::RefCell;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+use tracing_subscriber::EnvFilter;
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// One JSON object per line with the fields of the event and its span.
+    Json,
+}
+
+/// Logs to stderr, the level is set by `RUST_LOG`.
+pub fn init(format: LogFormat) {
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_writer(std::io::stderr);
+
+    match format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .init(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Connect,
+    Read,
+    Parse,
+}
+
+/// Time spent in every phase of collecting one target.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Phases {
+    pub connect: Option<Duration>,
+    pub read: Option<Duration>,
+    pub parse: Option<Duration>,
+}
+
+impl Phases {
+    fn add(&mut self, phase: Phase, elapsed: Duration) {
+        let total = match phase {
+            Phase::Connect => &mut self.connect,
+            Phase::Read => &mut self.read,
+            Phase::Parse => &mut self.parse,
+        };
+        *total = Some(total.unwrap_or_default() + elapsed);
+    }
+}
+
+pub fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+tokio::task_local! {
+    static PHASES: RefCell<Phases>;
+}
+
+/// Runs the collection of one target and sums up the phases timed within it.
+pub async fn phases<F: Future>(collect: F) -> (F::Output, Phases) {
+    PHASES
+        .scope(RefCell::new(Phases::default()), async {
+            let output = collect.await;
+            (output, PHASES.with(|p| *p.borrow()))
+        })
+        .await
+}
+
+/// Times a phase of the collection, phases repeated within a target add up.
+/// Outside of `phases` the time is not kept anywhere.
+pub async fn timed<F: Future>(phase: Phase, fut: F) -> F::Output {
+    let started = Instant::now();
+    let output = fut.await;
+    let _ = PHASES.try_with(|p| p.borrow_mut().add(phase, started.elapsed()));
+    output
+}
+
+/// `timed` for synchronous work such as parsing.
+pub fn timed_sync<T>(phase: Phase, f: impl FnOnce() -> T) -> T {
+    let started = Instant::now();
+    let output = f();
+    let _ = PHASES.try_with(|p| p.borrow_mut().add(phase, started.elapsed()));
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn phases_should_add_up_per_target() {
+        let collect = |delay: u64| async move {
+            let sleep = || tokio::time::sleep(Duration::from_millis(delay));
+            timed(Phase::Connect, sleep()).await;
+            timed(Phase::Read, sleep()).await;
+            timed(Phase::Read, sleep()).await;
+            timed_sync(Phase::Parse, || delay)
+        };
+
+        let ((fast, fast_phases), (slow, slow_phases)) =
+            tokio::join!(phases(collect(1)), phases(collect(30)));
+
+        assert_eq!((fast, slow), (1, 30));
+        assert!(fast_phases.connect.unwrap() < Duration::from_millis(30));
+        assert!(slow_phases.connect.unwrap() >= Duration::from_millis(30));
+        assert!(slow_phases.read.unwrap() >= Duration::from_millis(60));
+        assert!(slow_phases.parse.is_some());
+
+        // nothing is kept outside of a target
+        assert_eq!(timed_sync(Phase::Parse, || 42), 42);
+    }
+}
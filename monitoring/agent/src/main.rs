@@ -0,0 +1,426 @@
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Arc;
+
+use clap::{Parser, Subcommand};
+use reqwest::{Client, ClientBuilder};
+use time::OffsetDateTime;
+use tokio::net::TcpListener;
+use tokio::time::Duration;
+use tracing::{error, info, warn};
+
+use agent::agent::Agent;
+use agent::alertmanager::Alertmanager;
+use agent::checks;
+use agent::config;
+use agent::history::{self, Filter, History};
+use agent::juggler::Juggler;
+use agent::logging::{self, LogFormat};
+use agent::meta::{Heartbeat, SelfMonitoring};
+use agent::metrics::Metrics;
+use agent::pagerduty::PagerDuty;
+use agent::preflight;
+use agent::silence::{Action, Silence, SilenceFile, Silences};
+use agent::sink::Sink;
+use agent::state::State;
+use agent::status::{self, StatusBoard};
+
+const TIMEOUT: u64 = 3;
+
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    /// Path to the TOML config
+    #[arg(short, long, env = "AGENT_CONFIG", default_value = "agent.toml")]
+    config: PathBuf,
+
+    /// Log format, the level is set by RUST_LOG
+    #[arg(long, env = "AGENT_LOG_FORMAT", value_enum, default_value_t)]
+    log_format: LogFormat,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print status transitions kept in the local history
+    History {
+        #[arg(long)]
+        host: Option<String>,
+        #[arg(long)]
+        service: Option<String>,
+        /// RFC 3339, unix time or an age like 2h or 7d
+        #[arg(long, value_parser = history::parse_time)]
+        since: Option<i64>,
+        /// RFC 3339, unix time or an age like 2h or 7d
+        #[arg(long, value_parser = history::parse_time)]
+        until: Option<i64>,
+    },
+    /// Validate the config and try every target once, no events are sent
+    CheckConfig,
+    /// Manage silences kept in the silence file
+    Silence {
+        #[command(subcommand)]
+        command: SilenceCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum SilenceCommand {
+    /// Silence events of matching hosts and services
+    Add {
+        /// Juggler host, * matches any characters
+        #[arg(long, default_value = "*")]
+        host: String,
+        /// Juggler service, * matches any characters
+        #[arg(long, default_value = "*")]
+        service: String,
+        /// RFC 3339 or unix time, now by default
+        #[arg(long, value_parser = history::parse_time)]
+        starts: Option<i64>,
+        /// How long the silence lasts, e.g. 2h
+        #[arg(long = "for", value_parser = history::parse_duration)]
+        duration: Duration,
+        #[arg(long)]
+        reason: Option<String>,
+        /// Drop matching events instead of sending them as OK
+        #[arg(long)]
+        drop: bool,
+    },
+    /// List active and upcoming silences
+    List,
+    /// Remove a silence by its number in `list`
+    Remove { number: usize },
+}
+
+async fn open_history(config: &config::HistoryConfig) -> History {
+    let retention = Duration::from_secs(config.retention_days * 24 * 60 * 60);
+
+    match History::open(&config.path, retention).await {
+        Err(e) => {
+            error!("{e}");
+            process::exit(1)
+        }
+        Ok(history) => history,
+    }
+}
+
+fn open_state(path: &Path) -> Arc<State> {
+    match State::open(path) {
+        Err(e) => {
+            error!("{e}");
+            process::exit(1)
+        }
+        Ok(state) => Arc::new(state),
+    }
+}
+
+async fn print_history(history: &History, filter: Filter) {
+    let records = match history.query(&filter).await {
+        Err(e) => {
+            error!("failed to read history: {e}");
+            process::exit(1)
+        }
+        Ok(records) => records,
+    };
+
+    for r in history::transitions(records) {
+        let service = if r.instance.is_empty() {
+            r.service
+        } else {
+            format!("{}/{}", r.service, r.instance)
+        };
+        let description = r.description.replace('\n', " | ");
+        let status = if r.silenced {
+            format!("{} (silenced)", r.status)
+        } else {
+            r.status
+        };
+
+        println!(
+            "{} {} {} {} {}",
+            history::format_time(r.timestamp),
+            r.host,
+            service,
+            status,
+            description
+        );
+    }
+}
+
+fn print_silence(number: Option<usize>, silence: &Silence) {
+    let number = number.map_or(String::from("config"), |n| n.to_string());
+    let window = format!(
+        "{} - {}",
+        history::format_time(silence.starts.unix_timestamp()),
+        history::format_time(silence.ends.unix_timestamp())
+    );
+
+    println!(
+        "{number} {} {} {window} {:?} {}",
+        silence.host,
+        silence.service,
+        silence.action,
+        silence.reason.as_deref().unwrap_or_default()
+    );
+}
+
+fn manage_silences(config: &config::Config, command: SilenceCommand) -> Result<(), String> {
+    let path = config
+        .silence_file
+        .as_ref()
+        .ok_or("silence_file is not configured")?;
+    let now = OffsetDateTime::now_utc().replace_nanosecond(0).unwrap();
+
+    let mut file = SilenceFile::load(path)?;
+    file.prune(now);
+
+    match command {
+        SilenceCommand::Add {
+            host,
+            service,
+            starts,
+            duration,
+            reason,
+            drop,
+        } => {
+            let starts = match starts {
+                Some(unix) => OffsetDateTime::from_unix_timestamp(unix).map_err(|e| e.to_string())?,
+                None => now,
+            };
+
+            file.silence.push(Silence {
+                host,
+                service,
+                starts,
+                ends: starts + duration,
+                reason,
+                action: if drop { Action::Drop } else { Action::Downgrade },
+            });
+            file.save(path)?;
+            print_silence(Some(file.silence.len()), file.silence.last().unwrap());
+        }
+        SilenceCommand::List => {
+            for silence in config.silence.iter().filter(|s| s.ends > now) {
+                print_silence(None, silence);
+            }
+            for (i, silence) in file.silence.iter().enumerate() {
+                print_silence(Some(i + 1), silence);
+            }
+        }
+        SilenceCommand::Remove { number } => {
+            if number == 0 || number > file.silence.len() {
+                return Err(format!("there is no silence {number}"));
+            }
+            file.silence.remove(number - 1);
+            file.save(path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints config problems and a pass/fail table of every target, true when all is well.
+async fn check_config(config: &config::Config, client: Client) -> bool {
+    let problems = config.validate();
+    for problem in &problems {
+        println!("config: {problem}");
+    }
+
+    // trying the targets must not touch what the running agent remembers
+    let checks = match checks::registry(config, &Arc::new(State::memory())) {
+        Err(e) => {
+            println!("config: failed to set up checks: {e}");
+            return false;
+        }
+        Ok(checks) => checks,
+    };
+
+    let mut outcomes = vec![preflight::check_juggler(client, &config.juggler).await];
+    outcomes.extend(preflight::check_targets(&checks).await);
+    print!("{}", preflight::table(&outcomes));
+
+    problems.is_empty() && outcomes.iter().all(|o| o.result.is_ok())
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+
+    logging::init(cli.log_format);
+
+    let config = match config::load(&cli.config) {
+        Err(e) => {
+            error!("{e}");
+            process::exit(1)
+        }
+        Ok(config) => config,
+    };
+
+    // a zero interval or limit would panic or be ignored deep in a run,
+    // `check-config` lists the problems on its own
+    if cli.command.is_none() {
+        let problems = config.validate();
+        for problem in &problems {
+            error!("config: {problem}");
+        }
+        if !problems.is_empty() {
+            process::exit(1)
+        }
+    }
+
+    let client = ClientBuilder::new()
+        .timeout(Duration::from_secs(TIMEOUT))
+        .build()
+        .unwrap();
+
+    match cli.command {
+        Some(Command::History {
+            host,
+            service,
+            since,
+            until,
+        }) => {
+            let Some(history_config) = &config.history else {
+                error!("history is not configured");
+                process::exit(1)
+            };
+
+            let filter = Filter {
+                host,
+                service,
+                since,
+                until,
+            };
+            print_history(&open_history(history_config).await, filter).await;
+            return;
+        }
+        Some(Command::CheckConfig) => {
+            if !check_config(&config, client.clone()).await {
+                process::exit(1)
+            }
+            return;
+        }
+        Some(Command::Silence { command }) => {
+            if let Err(e) = manage_silences(&config, command) {
+                error!("{e}");
+                process::exit(1)
+            }
+            return;
+        }
+        None => {}
+    }
+
+    let state = open_state(&config.state_file);
+    let checks = match checks::registry(&config, &state) {
+        Err(e) => {
+            error!("failed to set up checks: {e}");
+            process::exit(1)
+        }
+        Ok(checks) => checks,
+    };
+
+    let juggler = Juggler::new(
+        client.clone(),
+        config.juggler.url.clone(),
+        config.juggler.source.clone(),
+    )
+    .with_batching(config.juggler.batching);
+    let mut sinks: Vec<Box<dyn Sink>> = vec![Box::new(juggler)];
+
+    if let Some(alertmanager) = &config.alertmanager {
+        let sink = Alertmanager::new(
+            client.clone(),
+            alertmanager.url.clone(),
+            config.juggler.source.clone(),
+            Duration::from_secs(alertmanager.ttl_seconds),
+        );
+        sinks.push(Box::new(sink.with_state(state.clone())));
+    }
+
+    if let Some(pagerduty) = &config.pagerduty {
+        let routing_key = match pagerduty.routing_key() {
+            Err(e) => {
+                error!("{e}");
+                process::exit(1)
+            }
+            Ok(key) => key,
+        };
+        let sink = PagerDuty::new(
+            client.clone(),
+            pagerduty.url.clone(),
+            routing_key,
+            config.juggler.source.clone(),
+        );
+        sinks.push(Box::new(sink.with_state(state.clone())));
+    }
+
+    for metrics in config.metrics {
+        sinks.push(Box::new(Metrics::new(metrics, client.clone())));
+    }
+
+    if let Some(history_config) = &config.history {
+        sinks.push(Box::new(open_history(history_config).await));
+    }
+
+    let silences = Silences::new(config.silence.clone(), config.silence_file.clone());
+    let meta = SelfMonitoring::new(
+        config
+            .self_monitoring
+            .host
+            .clone()
+            .unwrap_or_else(|| config.juggler.source.clone()),
+        config.version.clone().unwrap_or_default(),
+    );
+    let mut agent = Agent::new(checks, sinks)
+        .with_silences(silences)
+        .with_self_monitoring(meta);
+
+    if let Some(url) = &config.self_monitoring.heartbeat_url {
+        agent = agent.with_heartbeat(Heartbeat::new(client.clone(), url.clone()));
+    }
+
+    let Some(interval) = config.interval else {
+        if config.status.is_some() {
+            warn!("status page is served only with an interval, ignoring it");
+        }
+
+        if let Err(e) = agent.run_once().await {
+            error!("{e}");
+            process::exit(1)
+        }
+        info!("monitoring completed successfully.");
+        return;
+    };
+
+    if let Some(status) = &config.status {
+        let listener = match TcpListener::bind(&status.listen).await {
+            Err(e) => {
+                error!("failed to listen on {}: {e}", status.listen);
+                process::exit(1)
+            }
+            Ok(listener) => listener,
+        };
+        info!("serving status page on {}", status.listen);
+
+        let board = Arc::new(StatusBoard::default());
+        agent = agent.with_status(board.clone());
+
+        tokio::spawn(async move {
+            if let Err(e) = status::serve(listener, board).await {
+                error!("status page failed: {e}");
+            }
+        });
+    }
+
+    let mut ticker = tokio::time::interval(Duration::from_secs(interval));
+    loop {
+        ticker.tick().await;
+
+        match agent.run_once().await {
+            Err(e) => error!("{e}"),
+            Ok(_) => info!("monitoring completed successfully."),
+        }
+    }
+}
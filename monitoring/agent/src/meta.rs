@@ -0,0 +1,154 @@
+use std::time::Duration;
+
+use reqwest::Client;
+
+use crate::check::Report;
+use crate::juggler::{metrics, Event, Status};
+use crate::BoxError;
+
+pub const SERVICE: &str = "monitoring-self";
+
+/// Builds the `monitoring-self` event the agent sends about its own run.
+pub struct SelfMonitoring {
+    host: String,
+    version: String,
+}
+
+impl SelfMonitoring {
+    pub fn new(host: String, version: String) -> Self {
+        SelfMonitoring { host, version }
+    }
+
+    /// CRIT when no host could be collected, WARN when some could not.
+    /// `duration` covers the checks only, not sending their events.
+    pub fn event(&self, reports: &[Report], duration: Duration) -> Event {
+        let failed: Vec<String> = reports
+            .iter()
+            .flat_map(|report| {
+                report.targets.iter().filter_map(move |t| {
+                    let error = t.error.as_ref()?;
+                    Some(format!(
+                        "{}/{} {}: {error}",
+                        report.kind, report.cluster, t.target
+                    ))
+                })
+            })
+            .collect();
+        let attempted: usize = reports.iter().map(|r| r.targets.len()).sum();
+        let succeeded = attempted - failed.len();
+
+        let status = if succeeded == 0 {
+            Status::CRIT
+        } else if !failed.is_empty() {
+            Status::WARN
+        } else {
+            Status::OK
+        };
+
+        let mut description = format!(
+            "hosts succeeded: {succeeded}/{attempted} in {:.3}s, config version {}",
+            duration.as_secs_f64(),
+            self.version
+        );
+        for line in &failed {
+            description.push('\n');
+            description.push_str(line);
+        }
+
+        Event {
+            host: self.host.clone(),
+            service: String::from(SERVICE),
+            instance: String::from(""),
+            status,
+            description,
+            tags: vec![String::from("monitoring")],
+            metrics: metrics(&[
+                ("duration_seconds", duration.as_secs_f64()),
+                ("hosts_attempted", attempted as f64),
+                ("hosts_succeeded", succeeded as f64),
+            ]),
+            cluster_level: false,
+        }
+    }
+}
+
+/// Dead man's switch: the receiving side alerts when the pings stop.
+pub struct Heartbeat {
+    client: Client,
+    url: String,
+}
+
+impl Heartbeat {
+    pub fn new(client: Client, url: String) -> Self {
+        Heartbeat { client, url }
+    }
+
+    pub async fn beat(&self) -> Result<(), BoxError> {
+        self.client
+            .get(&self.url)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::TargetResult;
+
+    fn report(kind: &'static str, errors: &[Option<&str>]) -> Report {
+        Report {
+            kind,
+            cluster: String::from("main"),
+            targets: errors
+                .iter()
+                .enumerate()
+                .map(|(i, error)| TargetResult {
+                    target: format!("host{}", i + 1),
+                    host: format!("host{}-test", i + 1),
+                    latency: Duration::from_millis(5),
+                    error: error.map(String::from),
+                })
+                .collect(),
+            events: vec![],
+        }
+    }
+
+    fn monitoring() -> SelfMonitoring {
+        SelfMonitoring::new(String::from("agent"), String::from("v42"))
+    }
+
+    #[test]
+    fn failed_hosts_should_warn() {
+        let reports = [
+            report("zoo", &[None, Some("connection refused"), None]),
+            report("rmq", &[None]),
+        ];
+
+        let event = monitoring().event(&reports, Duration::from_millis(1500));
+
+        assert_eq!(event.service, "monitoring-self");
+        assert_eq!(event.status, Status::WARN);
+        assert_eq!(
+            event.description,
+            "hosts succeeded: 3/4 in 1.500s, config version v42\nzoo/main host2: connection refused"
+        );
+        assert_eq!(event.metrics["hosts_attempted"], 4.0);
+        assert_eq!(event.metrics["hosts_succeeded"], 3.0);
+        assert_eq!(event.metrics["duration_seconds"], 1.5);
+    }
+
+    #[test]
+    fn nothing_collected_should_be_critical() {
+        let event = monitoring().event(&[report("zoo", &[Some("timed out")])], Duration::ZERO);
+        assert_eq!(event.status, Status::CRIT);
+
+        let event = monitoring().event(&[], Duration::ZERO);
+        assert_eq!(event.status, Status::CRIT);
+
+        let event = monitoring().event(&[report("zoo", &[None])], Duration::ZERO);
+        assert_eq!(event.status, Status::OK);
+    }
+}
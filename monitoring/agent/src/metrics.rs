@@ -0,0 +1,355 @@
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use reqwest::Client;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::time::{timeout, Duration};
+
+use crate::config::{MetricsConfig, Protocol};
+use crate::juggler::{Event, Status};
+use crate::silence;
+use crate::sink::Sink;
+use crate::BoxError;
+
+const CONNECT_TIMEOUT: u64 = 2000;
+// keeps StatsD datagrams below a common MTU
+const MAX_DATAGRAM: usize = 1400;
+
+// path components keep a stable order whatever the tag names are
+const FIELDS: [&str; 3] = ["host", "service", "instance"];
+
+fn field<'a>(event: &'a Event, name: &str) -> &'a str {
+    match name {
+        "host" => &event.host,
+        "service" => &event.service,
+        "instance" => &event.instance,
+        _ => "",
+    }
+}
+
+fn sanitize(component: &str) -> String {
+    component
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// `prefix.host.service.instance.metric`, with unmapped and empty fields skipped.
+fn path(config: &MetricsConfig, event: &Event, metric: &str) -> String {
+    let mut components = vec![config.prefix.clone()];
+    for name in FIELDS {
+        let value = field(event, name);
+        if config.tags.contains_key(name) && !value.is_empty() {
+            components.push(sanitize(value));
+        }
+    }
+    components.push(sanitize(metric));
+
+    components.retain(|c| !c.is_empty());
+    components.join(".")
+}
+
+/// Metric values of an event and its `status` as 0 (OK), 1 (WARN) or 2 (CRIT),
+/// a silenced one also gets `silenced` 1.
+fn values(event: &Event) -> Vec<(&str, f64)> {
+    let status = match event.status {
+        Status::OK => 0.0,
+        Status::WARN => 1.0,
+        Status::CRIT => 2.0,
+    };
+
+    let mut values: Vec<(&str, f64)> = event
+        .metrics
+        .iter()
+        .map(|(metric, value)| (metric.as_str(), *value))
+        .collect();
+    values.push(("status", status));
+    if silence::is_silenced(event) {
+        values.push(("silenced", 1.0));
+    }
+    values
+}
+
+pub fn graphite_lines(config: &MetricsConfig, events: &[Event], timestamp: u64) -> Vec<String> {
+    events
+        .iter()
+        .flat_map(|event| {
+            values(event).into_iter().map(move |(metric, value)| {
+                format!("{} {value} {timestamp}", path(config, event, metric))
+            })
+        })
+        .collect()
+}
+
+pub fn statsd_lines(config: &MetricsConfig, events: &[Event]) -> Vec<String> {
+    events
+        .iter()
+        .flat_map(|event| {
+            values(event)
+                .into_iter()
+                .map(move |(metric, value)| format!("{}:{value}|g", path(config, event, metric)))
+        })
+        .collect()
+}
+
+fn escape_tag(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace('=', "\\=")
+        .replace(' ', "\\ ")
+}
+
+/// One point per event: the prefix is the measurement, metrics and status are the fields.
+pub fn influx_lines(config: &MetricsConfig, events: &[Event], timestamp: u64) -> Vec<String> {
+    events
+        .iter()
+        .map(|event| {
+            let mut tags: BTreeMap<&str, &str> = config
+                .extra_tags
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect();
+            for (name, tag) in &config.tags {
+                let value = field(event, name);
+                if !value.is_empty() {
+                    tags.insert(tag, value);
+                }
+            }
+
+            let tags: String = tags
+                .iter()
+                .map(|(k, v)| format!(",{}={}", escape_tag(k), escape_tag(v)))
+                .collect();
+            let fields: Vec<String> = values(event)
+                .iter()
+                .map(|(k, v)| format!("{}={v}", escape_tag(k)))
+                .collect();
+
+            format!(
+                "{}{tags} {} {}",
+                escape_tag(&config.prefix),
+                fields.join(","),
+                timestamp * 1_000_000_000
+            )
+        })
+        .collect()
+}
+
+/// Sends the raw metric values and the status of every event to a time-series backend.
+pub struct Metrics {
+    config: MetricsConfig,
+    client: Client,
+}
+
+impl Metrics {
+    pub fn new(config: MetricsConfig, client: Client) -> Self {
+        Metrics { config, client }
+    }
+
+    async fn graphite(&self, lines: Vec<String>) -> Result<(), BoxError> {
+        let mut conn = timeout(
+            Duration::from_millis(CONNECT_TIMEOUT),
+            TcpStream::connect(&self.config.address),
+        )
+        .await
+        .map_err(|_| format!("timed out connecting to {}", self.config.address))??;
+
+        let mut payload = lines.join("\n");
+        payload.push('\n');
+        conn.write_all(payload.as_bytes()).await?;
+        conn.shutdown().await?;
+        Ok(())
+    }
+
+    async fn statsd(&self, lines: Vec<String>) -> Result<(), BoxError> {
+        let socket = UdpSocket::bind("0.0.0.0:0").await?;
+        socket.connect(&self.config.address).await?;
+
+        let mut datagram = String::new();
+        for line in lines {
+            if !datagram.is_empty() && datagram.len() + line.len() + 1 > MAX_DATAGRAM {
+                socket.send(datagram.as_bytes()).await?;
+                datagram.clear();
+            }
+            if !datagram.is_empty() {
+                datagram.push('\n');
+            }
+            datagram.push_str(&line);
+        }
+        socket.send(datagram.as_bytes()).await?;
+        Ok(())
+    }
+
+    async fn influx(&self, lines: Vec<String>) -> Result<(), BoxError> {
+        self.client
+            .post(&self.config.address)
+            .body(lines.join("\n"))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Sink for Metrics {
+    fn name(&self) -> &str {
+        match self.config.protocol {
+            Protocol::Graphite => "graphite",
+            Protocol::Statsd => "statsd",
+            Protocol::Influx => "influx",
+        }
+    }
+
+    fn records(&self) -> bool {
+        true
+    }
+
+    async fn send(&self, events: &[Event]) -> Result<(), BoxError> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        let lines = match self.config.protocol {
+            Protocol::Graphite => graphite_lines(&self.config, events, timestamp),
+            Protocol::Statsd => statsd_lines(&self.config, events),
+            Protocol::Influx => influx_lines(&self.config, events, timestamp),
+        };
+
+        if lines.is_empty() {
+            return Ok(());
+        }
+
+        match self.config.protocol {
+            Protocol::Graphite => self.graphite(lines).await,
+            Protocol::Statsd => self.statsd(lines).await,
+            Protocol::Influx => self.influx(lines).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::juggler::metrics;
+
+    fn config(protocol: &str, extra: &str) -> MetricsConfig {
+        toml::from_str(&format!(
+            "protocol = \"{protocol}\"\naddress = \"127.0.0.1:2003\"\n{extra}"
+        ))
+        .unwrap()
+    }
+
+    fn events() -> Vec<Event> {
+        vec![
+            Event {
+                host: String::from("zoo1.example.com"),
+                service: String::from("state"),
+                instance: String::from(""),
+                status: Status::OK,
+                description: String::new(),
+                tags: vec![],
+                metrics: metrics(&[("zk_avg_latency", 0.5), ("zk_znode_count", 42.0)]),
+                cluster_level: false,
+            },
+            Event {
+                host: String::from("rmq1-test"),
+                service: String::from("shovel"),
+                instance: String::from("orders to dc2"),
+                status: Status::CRIT,
+                description: String::new(),
+                tags: vec![],
+                metrics: metrics(&[("running", 0.0)]),
+                cluster_level: false,
+            },
+        ]
+    }
+
+    #[test]
+    fn graphite_paths_should_be_sanitized() {
+        let lines = graphite_lines(&config("graphite", ""), &events(), 1700000000);
+
+        assert_eq!(
+            lines,
+            vec![
+                "monitoring.zoo1_example_com.state.zk_avg_latency 0.5 1700000000",
+                "monitoring.zoo1_example_com.state.zk_znode_count 42 1700000000",
+                "monitoring.zoo1_example_com.state.status 0 1700000000",
+                "monitoring.rmq1-test.shovel.orders_to_dc2.running 0 1700000000",
+                "monitoring.rmq1-test.shovel.orders_to_dc2.status 2 1700000000",
+            ]
+        );
+    }
+
+    #[test]
+    fn statsd_should_skip_unmapped_fields() {
+        let config = config("statsd", "prefix = \"zk\"\ntags = { service = \"check\" }");
+        let lines = statsd_lines(&config, &events()[..1]);
+
+        assert_eq!(
+            lines,
+            vec![
+                "zk.state.zk_avg_latency:0.5|g",
+                "zk.state.zk_znode_count:42|g",
+                "zk.state.status:0|g",
+            ]
+        );
+    }
+
+    #[test]
+    fn influx_should_map_tags() {
+        let config = config(
+            "influx",
+            "tags = { host = \"server\", instance = \"name\" }\nextra_tags = { env = \"prod\" }",
+        );
+        let lines = influx_lines(&config, &events(), 1);
+
+        assert_eq!(
+            lines,
+            vec![
+                "monitoring,env=prod,server=zoo1.example.com zk_avg_latency=0.5,zk_znode_count=42,status=0 1000000000",
+                "monitoring,env=prod,name=orders\\ to\\ dc2,server=rmq1-test running=0,status=2 1000000000",
+            ]
+        );
+    }
+
+    #[test]
+    fn silenced_events_should_be_flagged() {
+        let mut events = events();
+        events[1].tags.push(String::from(silence::SILENCED));
+        let lines = graphite_lines(&config("graphite", ""), &events[1..], 1);
+
+        assert_eq!(
+            lines,
+            vec![
+                "monitoring.rmq1-test.shovel.orders_to_dc2.running 0 1",
+                "monitoring.rmq1-test.shovel.orders_to_dc2.status 2 1",
+                "monitoring.rmq1-test.shovel.orders_to_dc2.silenced 1 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn every_event_should_get_a_status() {
+        let mut events = events();
+        events[0].status = Status::WARN;
+        events[0].metrics.clear();
+        let lines = statsd_lines(&config("statsd", ""), &events);
+
+        assert_eq!(
+            lines,
+            vec![
+                "monitoring.zoo1_example_com.state.status:1|g",
+                "monitoring.rmq1-test.shovel.orders_to_dc2.running:0|g",
+                "monitoring.rmq1-test.shovel.orders_to_dc2.status:2|g",
+            ]
+        );
+    }
+}
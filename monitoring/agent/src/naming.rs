@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use hickory_resolver::TokioAsyncResolver;
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::check::{DynCheck, Report};
+use crate::BoxError;
+
+/// How a monitored host becomes the host of a Juggler event.
+///
+/// Templates substitute `{hostname}` (the first label of a name or the whole
+/// IP address), `{host}` (the host without a port) and `{cluster}`.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "strategy", rename_all = "kebab-case")]
+pub enum HostNaming {
+    Template {
+        #[serde(default = "default_template")]
+        template: String,
+    },
+    /// Explicit aliases keyed by host, the template is used for the rest.
+    Alias {
+        aliases: HashMap<String, String>,
+        #[serde(default = "default_template")]
+        fallback: String,
+    },
+    /// IP addresses are resolved to their PTR name before the template is filled,
+    /// the address itself is used when the lookup fails.
+    ReverseDns {
+        #[serde(default = "default_template")]
+        template: String,
+    },
+}
+
+impl Default for HostNaming {
+    fn default() -> Self {
+        Self::Template {
+            template: default_template(),
+        }
+    }
+}
+
+fn default_template() -> String {
+    String::from("{hostname}-test")
+}
+
+/// Fills a Juggler host template for a monitored host.
+pub fn juggler_host(template: &str, cluster: &str, host: &str) -> String {
+    let host = strip_port(host);
+    let hostname = match host.parse::<IpAddr>() {
+        Ok(_) => host,
+        Err(_) => host.split('.').next().unwrap(),
+    };
+
+    template
+        .replace("{hostname}", hostname)
+        .replace("{host}", host)
+        .replace("{cluster}", cluster)
+}
+
+fn strip_port(host: &str) -> &str {
+    let host = match host.rsplit_once(':') {
+        // a bare IPv6 address has no port
+        Some(_) if host.parse::<IpAddr>().is_ok() => host,
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    };
+
+    host.trim_start_matches('[').trim_end_matches(']')
+}
+
+// probes take URLs as targets, their events carry the URL host
+fn target_host(target: &str) -> &str {
+    match target.split_once("://") {
+        Some((_, rest)) => {
+            let authority = rest.split(['/', '?', '#']).next().unwrap();
+            let authority = authority.rsplit('@').next().unwrap();
+            strip_port(authority)
+        }
+        None => target,
+    }
+}
+
+/// Host of a target without scheme, credentials, path or port.
+pub fn bare_host(target: &str) -> &str {
+    strip_port(target_host(target))
+}
+
+pub fn resolver() -> Result<Arc<TokioAsyncResolver>, BoxError> {
+    Ok(Arc::new(TokioAsyncResolver::tokio_from_system_conf()?))
+}
+
+/// Applies a naming strategy for one cluster.
+pub struct Namer {
+    naming: HostNaming,
+    cluster: String,
+    resolver: Option<Arc<TokioAsyncResolver>>,
+    // PTR names of the addresses resolved so far
+    cache: Mutex<HashMap<IpAddr, String>>,
+}
+
+impl Namer {
+    pub fn new(
+        naming: HostNaming,
+        cluster: &str,
+        resolver: Option<Arc<TokioAsyncResolver>>,
+    ) -> Self {
+        Self {
+            naming,
+            cluster: cluster.to_string(),
+            resolver,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn juggler_host(&self, host: &str) -> String {
+        match &self.naming {
+            HostNaming::Template { template } => juggler_host(template, &self.cluster, host),
+            HostNaming::Alias { aliases, fallback } => aliases
+                .get(host)
+                .or_else(|| aliases.get(strip_port(host)))
+                .cloned()
+                .unwrap_or_else(|| juggler_host(fallback, &self.cluster, host)),
+            HostNaming::ReverseDns { template } => {
+                let name = self.reverse(host).await;
+                juggler_host(template, &self.cluster, &name)
+            }
+        }
+    }
+
+    async fn reverse(&self, host: &str) -> String {
+        let (Ok(ip), Some(resolver)) = (strip_port(host).parse::<IpAddr>(), &self.resolver) else {
+            return host.to_string();
+        };
+
+        if let Some(name) = self.cache.lock().unwrap().get(&ip) {
+            return name.clone();
+        }
+
+        match resolver.reverse_lookup(ip).await {
+            Ok(lookup) => match lookup.iter().next() {
+                Some(ptr) => {
+                    let name = ptr.to_string().trim_end_matches('.').to_string();
+                    self.cache.lock().unwrap().insert(ip, name.clone());
+                    name
+                }
+                None => host.to_string(),
+            },
+            Err(e) => {
+                warn!("{}: reverse lookup of {ip} failed: {e}", self.cluster);
+                host.to_string()
+            }
+        }
+    }
+}
+
+/// A check whose event hosts are renamed by its cluster's naming strategy.
+/// Cluster-level events keep the cluster name as their host.
+pub struct Named {
+    check: Box<dyn DynCheck>,
+    namer: Namer,
+}
+
+impl Named {
+    pub fn new(check: Box<dyn DynCheck>, namer: Namer) -> Self {
+        Self { check, namer }
+    }
+}
+
+#[async_trait]
+impl DynCheck for Named {
+    fn kind(&self) -> &'static str {
+        self.check.kind()
+    }
+
+    fn cluster(&self) -> &str {
+        self.check.cluster()
+    }
+
+    async fn run(&self) -> Report {
+        let mut report = self.check.run().await;
+
+        for target in &mut report.targets {
+            target.host = self.namer.juggler_host(target_host(&target.target)).await;
+        }
+
+        for event in &mut report.events {
+            if !event.cluster_level {
+                event.host = self.namer.juggler_host(&event.host).await;
+            }
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::Check;
+    use crate::juggler::{Event, Status};
+
+    // a probe named after the host it probes, plus one event of the whole cluster
+    struct Api;
+
+    #[async_trait]
+    impl Check for Api {
+        type Sample = ();
+
+        fn kind(&self) -> &'static str {
+            "http"
+        }
+
+        fn cluster(&self) -> &str {
+            "api"
+        }
+
+        async fn targets(&self) -> Vec<String> {
+            vec![String::from("http://api:8080/health")]
+        }
+
+        async fn collect(&self, _target: &str) -> Result<(), BoxError> {
+            Ok(())
+        }
+
+        fn evaluate(&self, _samples: Vec<(String, ())>) -> Vec<Event> {
+            [false, true]
+                .into_iter()
+                .map(|cluster_level| Event {
+                    host: String::from("api"),
+                    service: String::from("api"),
+                    instance: String::from(""),
+                    status: Status::OK,
+                    description: String::new(),
+                    tags: vec![],
+                    metrics: Default::default(),
+                    cluster_level,
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn template_should_be_filled() {
+        assert_eq!(
+            juggler_host("{hostname}-test", "main", "zoo1.example.com"),
+            "zoo1-test"
+        );
+        assert_eq!(
+            juggler_host("{cluster}.{host}", "main", "zoo1.example.com:2181"),
+            "main.zoo1.example.com"
+        );
+    }
+
+    #[test]
+    fn addresses_should_be_kept_whole() {
+        assert_eq!(
+            juggler_host("{hostname}-test", "main", "10.0.0.1:2181"),
+            "10.0.0.1-test"
+        );
+        assert_eq!(juggler_host("{hostname}", "main", "[::1]:2181"), "::1");
+        assert_eq!(juggler_host("{hostname}", "main", "fe80::1"), "fe80::1");
+    }
+
+    #[test]
+    fn url_targets_should_be_reduced_to_host() {
+        assert_eq!(target_host("https://api.example.com:8443/health"), "api.example.com");
+        assert_eq!(target_host("http://user@[::1]/"), "::1");
+        assert_eq!(target_host("zoo1.example.com:2181"), "zoo1.example.com:2181");
+    }
+
+    #[tokio::test]
+    async fn aliases_should_win_over_fallback() {
+        let naming = HostNaming::Alias {
+            aliases: HashMap::from([(String::from("10.0.0.1"), String::from("zoo1"))]),
+            fallback: String::from("{cluster}-{hostname}"),
+        };
+        let namer = Namer::new(naming, "main", None);
+
+        assert_eq!(namer.juggler_host("10.0.0.1:2181").await, "zoo1");
+        assert_eq!(namer.juggler_host("10.0.0.2").await, "main-10.0.0.2");
+    }
+
+    #[tokio::test]
+    async fn reverse_dns_should_fall_back_to_host() {
+        let naming = HostNaming::ReverseDns {
+            template: default_template(),
+        };
+        let namer = Namer::new(naming, "main", None);
+
+        assert_eq!(namer.juggler_host("zoo1.example.com").await, "zoo1-test");
+        assert_eq!(namer.juggler_host("10.0.0.1").await, "10.0.0.1-test");
+    }
+
+    #[tokio::test]
+    async fn members_named_like_the_cluster_should_be_renamed() {
+        let namer = Namer::new(HostNaming::default(), "api", None);
+        let report = Named::new(Box::new(Api), namer).run().await;
+
+        assert_eq!(report.targets[0].host, "api-test");
+        assert_eq!(report.events[0].host, "api-test");
+        // only the cluster-level event keeps the cluster name
+        assert_eq!(report.events[1].host, "api");
+    }
+}
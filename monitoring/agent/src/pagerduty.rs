@@ -0,0 +1,214 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::juggler::{Event, Status};
+use crate::secret::Secret;
+use crate::sink::{Change, Firing, Sink};
+use crate::state::State;
+use crate::BoxError;
+
+pub const PAGERDUTY_URL: &str = "https://events.pagerduty.com";
+
+// PagerDuty rejects longer summaries
+const MAX_SUMMARY: usize = 1024;
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Payload {
+    pub summary: String,
+    pub source: String,
+    pub severity: &'static str,
+    pub component: String,
+    pub group: String,
+    pub custom_details: serde_json::Value,
+}
+
+/// One event of `POST /v2/enqueue`.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct PagerDutyEvent {
+    pub routing_key: String,
+    pub event_action: &'static str,
+    pub dedup_key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<Payload>,
+}
+
+pub fn dedup_key(source: &str, event: &Event) -> String {
+    format!("{source}/{}/{}/{}", event.host, event.service, event.instance)
+}
+
+/// PagerDuty incidents are keyed by `dedup_key` alone, so a changed status
+/// re-triggers the same incident and only an OK event resolves it.
+pub fn pagerdutify(routing_key: &str, source: &str, change: &Change) -> Option<PagerDutyEvent> {
+    match change {
+        Change::Trigger(event) => {
+            let mut summary = format!(
+                "{} on {} is {:?}: {}",
+                event.service,
+                event.host,
+                event.status,
+                event.description.lines().next().unwrap_or_default()
+            );
+            summary.truncate(summary.floor_char_boundary(MAX_SUMMARY));
+
+            Some(PagerDutyEvent {
+                routing_key: routing_key.to_string(),
+                event_action: "trigger",
+                dedup_key: dedup_key(source, event),
+                payload: Some(Payload {
+                    summary,
+                    source: event.host.clone(),
+                    severity: event.status.severity(),
+                    component: event.service.clone(),
+                    group: source.to_string(),
+                    custom_details: json!({
+                        "description": event.description,
+                        "instance": event.instance,
+                        "tags": event.tags,
+                    }),
+                }),
+            })
+        }
+        Change::Resolve { event, .. } if event.status == Status::OK => Some(PagerDutyEvent {
+            routing_key: routing_key.to_string(),
+            event_action: "resolve",
+            dedup_key: dedup_key(source, event),
+            payload: None,
+        }),
+        Change::Resolve { .. } => None,
+    }
+}
+
+/// PagerDuty Events API v2 client.
+pub struct PagerDuty {
+    client: Client,
+    url: String,
+    routing_key: Secret,
+    source: String,
+    firing: Firing,
+}
+
+impl PagerDuty {
+    pub fn new(client: Client, url: String, routing_key: Secret, source: String) -> Self {
+        PagerDuty {
+            client,
+            url,
+            routing_key,
+            source,
+            firing: Firing::new("pagerduty/firing", Arc::new(State::memory())),
+        }
+    }
+
+    /// Remembers the open alerts in the agent state, so they are resolved after a restart.
+    pub fn with_state(mut self, state: Arc<State>) -> Self {
+        self.firing = Firing::new("pagerduty/firing", state);
+        self
+    }
+}
+
+#[async_trait]
+impl Sink for PagerDuty {
+    fn name(&self) -> &str {
+        "pagerduty"
+    }
+
+    // the API takes one event per request
+    async fn send(&self, events: &[Event]) -> Result<(), BoxError> {
+        let (changes, firing) = self.firing.changes(events);
+        let requests: Vec<PagerDutyEvent> = changes
+            .iter()
+            .filter_map(|change| pagerdutify(self.routing_key.expose(), &self.source, change))
+            .collect();
+
+        let mut failed = 0;
+        for request in &requests {
+            let sent = self
+                .client
+                .post(format!("{}/v2/enqueue", self.url))
+                .json(request)
+                .send()
+                .await
+                .and_then(|r| r.error_for_status());
+            if sent.is_err() {
+                failed += 1;
+            }
+        }
+
+        if failed > 0 {
+            return Err(format!("{failed}/{} events were not accepted", requests.len()).into());
+        }
+
+        self.firing.commit(firing);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(status: Status) -> Event {
+        Event {
+            host: String::from("rmq1-test"),
+            service: String::from("shovel"),
+            instance: String::from("orders"),
+            status,
+            description: String::from("Shovel orders on rmq1: terminated\nlast error: econnrefused"),
+            tags: vec![],
+            metrics: Default::default(),
+            cluster_level: false,
+        }
+    }
+
+    #[test]
+    fn trigger_should_carry_payload() {
+        let event = event(Status::CRIT);
+        let request = pagerdutify("key", "agent", &Change::Trigger(&event)).unwrap();
+
+        assert_eq!(request.event_action, "trigger");
+        assert_eq!(request.dedup_key, "agent/rmq1-test/shovel/orders");
+
+        let payload = request.payload.unwrap();
+        assert_eq!(
+            payload.summary,
+            "shovel on rmq1-test is CRIT: Shovel orders on rmq1: terminated"
+        );
+        assert_eq!(payload.severity, "critical");
+        assert_eq!(payload.custom_details["instance"], "orders");
+    }
+
+    #[test]
+    fn long_summary_should_be_cut_on_a_char_boundary() {
+        let mut event = event(Status::CRIT);
+        event.description = "é".repeat(MAX_SUMMARY);
+        let request = pagerdutify("key", "agent", &Change::Trigger(&event)).unwrap();
+
+        let summary = request.payload.unwrap().summary;
+        assert!(summary.len() <= MAX_SUMMARY);
+        assert!(summary.ends_with('é'));
+    }
+
+    #[test]
+    fn only_ok_should_resolve() {
+        let ok = event(Status::OK);
+        let resolve = Change::Resolve {
+            event: &ok,
+            previous: Status::CRIT,
+        };
+        let request = pagerdutify("key", "agent", &resolve).unwrap();
+
+        assert_eq!(request.event_action, "resolve");
+        assert_eq!(request.dedup_key, "agent/rmq1-test/shovel/orders");
+        assert!(request.payload.is_none());
+
+        let crit = event(Status::CRIT);
+        let escalated = Change::Resolve {
+            event: &crit,
+            previous: Status::WARN,
+        };
+        assert!(pagerdutify("key", "agent", &escalated).is_none());
+    }
+}
@@ -0,0 +1,153 @@
+use std::time::{Duration, Instant};
+
+use futures::future::join_all;
+use reqwest::Client;
+
+use crate::check::DynCheck;
+use crate::config::JugglerConfig;
+use crate::juggler::Juggler;
+use crate::naming;
+
+/// Whether one target could be reached, and how fast.
+#[derive(Debug)]
+pub struct Outcome {
+    pub kind: String,
+    pub cluster: String,
+    pub target: String,
+    pub result: Result<Duration, String>,
+}
+
+async fn resolve(target: &str) -> Result<(), String> {
+    let host = naming::bare_host(target);
+    let mut addrs = tokio::net::lookup_host((host, 0))
+        .await
+        .map_err(|e| format!("failed to resolve {host}: {e}"))?;
+
+    match addrs.next() {
+        Some(_) => Ok(()),
+        None => Err(format!("{host} has no addresses")),
+    }
+}
+
+/// Resolves every target and collects it once with the real protocol of its check.
+/// The events the checks compute are dropped.
+pub async fn check_targets(checks: &[Box<dyn DynCheck>]) -> Vec<Outcome> {
+    let reports = join_all(checks.iter().map(|check| check.run())).await;
+
+    let mut outcomes = Vec::new();
+    for report in reports {
+        let resolved = join_all(report.targets.iter().map(|t| resolve(&t.target))).await;
+
+        for (target, resolved) in report.targets.into_iter().zip(resolved) {
+            let result = match (resolved, target.error) {
+                (Err(e), _) => Err(e),
+                (Ok(()), Some(e)) => Err(e),
+                (Ok(()), None) => Ok(target.latency),
+            };
+
+            outcomes.push(Outcome {
+                kind: report.kind.to_string(),
+                cluster: report.cluster.clone(),
+                target: target.target,
+                result,
+            });
+        }
+    }
+
+    outcomes
+}
+
+/// Posts an empty batch, so Juggler answers without receiving any event.
+pub async fn check_juggler(client: Client, config: &JugglerConfig) -> Outcome {
+    let juggler = Juggler::new(client, config.url.clone(), config.source.clone());
+
+    let started = Instant::now();
+    let result = match juggler.send(&[]).await {
+        Ok(_) => Ok(started.elapsed()),
+        Err(e) => Err(e.to_string()),
+    };
+
+    Outcome {
+        kind: String::from("juggler"),
+        cluster: String::from("-"),
+        target: config.url.clone(),
+        result,
+    }
+}
+
+/// Aligned pass/fail table, one target per line.
+pub fn table(outcomes: &[Outcome]) -> String {
+    let mut rows = vec![[
+        String::from("CHECK"),
+        String::from("CLUSTER"),
+        String::from("TARGET"),
+        String::from("RESULT"),
+        String::from("LATENCY"),
+        String::from("ERROR"),
+    ]];
+    for o in outcomes {
+        let (result, latency, error) = match &o.result {
+            Ok(latency) => ("pass", format!("{}ms", latency.as_millis()), String::new()),
+            Err(e) => ("FAIL", String::from("-"), e.clone()),
+        };
+        rows.push([
+            o.kind.clone(),
+            o.cluster.clone(),
+            o.target.clone(),
+            result.to_string(),
+            latency,
+            error,
+        ]);
+    }
+
+    let mut widths = [0; 5];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    rows.iter()
+        .map(|row| {
+            let mut line: String = row[..5]
+                .iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{cell:width$}  "))
+                .collect();
+            line.push_str(&row[5]);
+            line.trim_end().to_string() + "\n"
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_should_be_aligned() {
+        let outcomes = [
+            Outcome {
+                kind: String::from("zoo"),
+                cluster: String::from("main"),
+                target: String::from("zoo1.example.com"),
+                result: Ok(Duration::from_millis(12)),
+            },
+            Outcome {
+                kind: String::from("juggler"),
+                cluster: String::from("-"),
+                target: String::from("http://juggler"),
+                result: Err(String::from("connection refused")),
+            },
+        ];
+
+        assert_eq!(
+            table(&outcomes),
+            "\
+CHECK    CLUSTER  TARGET            RESULT  LATENCY  ERROR
+zoo      main     zoo1.example.com  pass    12ms
+juggler  -        http://juggler    FAIL    -        connection refused
+"
+        );
+    }
+}
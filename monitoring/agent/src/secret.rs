@@ -0,0 +1,3 @@
+//! Secrets and credentials are shared with the `rmq` monitor.
+
+pub use rmq::secret::*;
@@ -0,0 +1,280 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::juggler::{Event, Status};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    /// Send the event as OK with the original status in the description.
+    #[default]
+    Downgrade,
+    /// Do not send the event at all.
+    Drop,
+}
+
+/// Silences events of matching hosts and services within a time window.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Silence {
+    /// Juggler host, `*` matches any characters.
+    #[serde(default = "any")]
+    pub host: String,
+    /// Juggler service, `*` matches any characters.
+    #[serde(default = "any")]
+    pub service: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub starts: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub ends: OffsetDateTime,
+    pub reason: Option<String>,
+    #[serde(default)]
+    pub action: Action,
+}
+
+fn any() -> String {
+    String::from("*")
+}
+
+fn glob(pattern: &str, value: &str) -> bool {
+    let pattern = format!("^{}$", regex::escape(pattern).replace("\\*", ".*"));
+    Regex::new(&pattern).is_ok_and(|re| re.is_match(value))
+}
+
+impl Silence {
+    pub fn is_active(&self, now: OffsetDateTime) -> bool {
+        self.starts <= now && now < self.ends
+    }
+
+    pub fn matches(&self, event: &Event) -> bool {
+        glob(&self.host, &event.host) && glob(&self.service, &event.service)
+    }
+
+    fn annotation(&self) -> String {
+        let ends = self.ends.format(&Rfc3339).unwrap_or_default();
+        match &self.reason {
+            Some(reason) => format!("silenced until {ends}: {reason}"),
+            None => format!("silenced until {ends}"),
+        }
+    }
+}
+
+/// Layout of the silence file, also used by the `[[silence]]` config tables.
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct SilenceFile {
+    #[serde(default)]
+    pub silence: Vec<Silence>,
+}
+
+impl SilenceFile {
+    /// A missing file has no silences.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(raw) => toml::from_str(&raw)
+                .map_err(|e| format!("failed to parse {}: {}", path.display(), e.message())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(SilenceFile::default()),
+            Err(e) => Err(format!("failed to read {}: {e}", path.display())),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let raw = toml::to_string(self).map_err(|e| e.to_string())?;
+        fs::write(path, raw).map_err(|e| format!("failed to write {}: {e}", path.display()))
+    }
+
+    /// Drops silences that are over.
+    pub fn prune(&mut self, now: OffsetDateTime) {
+        self.silence.retain(|s| s.ends > now);
+    }
+}
+
+/// Silences from the config and the silence file, the file is re-read on every run
+/// so changes made by the `silence` subcommand apply without a restart.
+#[derive(Debug, Default)]
+pub struct Silences {
+    rules: Vec<Silence>,
+    file: Option<PathBuf>,
+}
+
+impl Silences {
+    pub fn new(rules: Vec<Silence>, file: Option<PathBuf>) -> Self {
+        Silences { rules, file }
+    }
+
+    pub fn active(&self, now: OffsetDateTime) -> Result<Vec<Silence>, String> {
+        let mut silences = self.rules.clone();
+        if let Some(file) = &self.file {
+            silences.extend(SilenceFile::load(file)?.silence);
+        }
+        silences.retain(|s| s.is_active(now));
+        Ok(silences)
+    }
+}
+
+/// Tag of the events matched by a silence, see `mark`.
+pub const SILENCED: &str = "silenced";
+
+/// Tags the events matched by a silence, whatever its action, and keeps them as they are
+/// otherwise. Sinks recording every event get these instead of the ones from `apply`.
+pub fn mark(events: Vec<Event>, silences: &[Silence]) -> Vec<Event> {
+    events
+        .into_iter()
+        .map(|mut event| {
+            if silences.iter().any(|s| s.matches(&event)) {
+                event.tags.push(String::from(SILENCED));
+            }
+            event
+        })
+        .collect()
+}
+
+pub fn is_silenced(event: &Event) -> bool {
+    event.tags.iter().any(|tag| tag == SILENCED)
+}
+
+/// Downgrades or drops the events matched by a silence.
+pub fn apply(events: Vec<Event>, silences: &[Silence]) -> Vec<Event> {
+    events
+        .into_iter()
+        .filter_map(|mut event| {
+            let Some(silence) = silences.iter().find(|s| s.matches(&event)) else {
+                return Some(event);
+            };
+
+            match silence.action {
+                Action::Drop => None,
+                Action::Downgrade => {
+                    if event.status != Status::OK {
+                        event.description = format!(
+                            "{}, was {:?}\n{}",
+                            silence.annotation(),
+                            event.status,
+                            event.description
+                        );
+                        event.status = Status::OK;
+                    }
+                    Some(event)
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use time::Duration;
+
+    use super::*;
+
+    fn event(host: &str, service: &str, status: Status) -> Event {
+        Event {
+            host: host.to_string(),
+            service: service.to_string(),
+            instance: String::from(""),
+            status,
+            description: String::from("leader. followers: 1/2"),
+            tags: vec![],
+            metrics: BTreeMap::new(),
+            cluster_level: false,
+        }
+    }
+
+    fn silence(host: &str, service: &str, action: Action) -> Silence {
+        Silence {
+            host: host.to_string(),
+            service: service.to_string(),
+            starts: OffsetDateTime::UNIX_EPOCH,
+            ends: OffsetDateTime::UNIX_EPOCH + Duration::hours(1),
+            reason: Some(String::from("zoo upgrade")),
+            action,
+        }
+    }
+
+    #[test]
+    fn globs_should_match() {
+        assert!(glob("zoo*-test", "zoo1-test"));
+        assert!(glob("*", "anything"));
+        assert!(!glob("zoo*", "rmq1-test"));
+        assert!(!glob("zoo1.test", "zoo1-test"));
+    }
+
+    #[test]
+    fn matching_events_should_be_downgraded() {
+        let events = vec![
+            event("zoo1-test", "state", Status::WARN),
+            event("zoo1-test", "leader-election", Status::WARN),
+            event("rmq1-test", "state", Status::CRIT),
+        ];
+
+        let events = apply(events, &[silence("zoo*", "state", Action::Downgrade)]);
+
+        assert_eq!(events[0].status, Status::OK);
+        assert_eq!(
+            events[0].description,
+            "silenced until 1970-01-01T01:00:00Z: zoo upgrade, was WARN\nleader. followers: 1/2"
+        );
+        assert_eq!(events[1].status, Status::WARN);
+        assert_eq!(events[2].status, Status::CRIT);
+    }
+
+    #[test]
+    fn matching_events_should_be_dropped() {
+        let events = vec![
+            event("zoo1-test", "state", Status::WARN),
+            event("rmq1-test", "shovel", Status::CRIT),
+        ];
+
+        let events = apply(events, &[silence("*", "shovel", Action::Drop)]);
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].host, "zoo1-test");
+    }
+
+    #[test]
+    fn matching_events_should_be_marked_as_they_are() {
+        let events = vec![
+            event("zoo1-test", "state", Status::WARN),
+            event("rmq1-test", "shovel", Status::CRIT),
+        ];
+
+        let events = mark(events, &[silence("*", "shovel", Action::Drop)]);
+
+        assert_eq!(events.len(), 2);
+        assert!(!is_silenced(&events[0]));
+        assert!(is_silenced(&events[1]));
+        assert_eq!(events[1].status, Status::CRIT);
+        assert_eq!(events[1].description, "leader. followers: 1/2");
+    }
+
+    #[test]
+    fn silence_file_should_round_trip() {
+        let path = std::env::temp_dir().join(format!("agent-{}-silences.toml", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        assert!(SilenceFile::load(&path).unwrap().silence.is_empty());
+
+        let mut file = SilenceFile {
+            silence: vec![silence("zoo1-test", "*", Action::Drop)],
+        };
+        file.save(&path).unwrap();
+        assert_eq!(SilenceFile::load(&path).unwrap().silence, file.silence);
+
+        file.prune(OffsetDateTime::UNIX_EPOCH + Duration::hours(2));
+        assert!(file.silence.is_empty());
+
+        let silences = Silences::new(vec![], Some(path.clone()));
+        assert_eq!(silences.active(OffsetDateTime::UNIX_EPOCH).unwrap().len(), 1);
+        assert!(silences
+            .active(OffsetDateTime::UNIX_EPOCH + Duration::hours(1))
+            .unwrap()
+            .is_empty());
+
+        fs::remove_file(path).unwrap();
+    }
+}
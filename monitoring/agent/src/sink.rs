@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::juggler::{Event, Status};
+use crate::state::State;
+use crate::BoxError;
+
+/// Somewhere the events of a run end up.
+#[async_trait]
+pub trait Sink: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Recording sinks keep every event as it was collected, silenced ones tagged by
+    /// `silence::mark`. The others alert on events and get them after the silences.
+    fn records(&self) -> bool {
+        false
+    }
+
+    async fn send(&self, events: &[Event]) -> Result<(), BoxError>;
+}
+
+/// What an alerting sink has to do about an event.
+#[derive(Debug, PartialEq)]
+pub enum Change<'a> {
+    Trigger(&'a Event),
+    /// The alert raised with the `previous` status is over,
+    /// either because the event is OK now or because its status changed.
+    Resolve { event: &'a Event, previous: Status },
+}
+
+/// Open alerts by host, service and instance.
+pub type Alerts = HashMap<(String, String, String), Status>;
+
+/// Alerts an alerting sink has raised, so OK events only resolve what is open.
+/// They are kept in the agent state, so an alert raised by one run
+/// is resolved by the next one even after a restart.
+pub struct Firing {
+    key: String,
+    state: Arc<State>,
+}
+
+impl Firing {
+    pub fn new(key: &str, state: Arc<State>) -> Self {
+        Firing {
+            key: key.to_string(),
+            state,
+        }
+    }
+
+    /// What to send for `events` and the alerts open once it is sent,
+    /// nothing is remembered until they are `commit`ted.
+    pub fn changes<'a>(&self, events: &'a [Event]) -> (Vec<Change<'a>>, Alerts) {
+        // JSON keys are strings, so the alerts are kept as a list
+        let mut firing: Alerts = self
+            .state
+            .get::<Vec<((String, String, String), Status)>>(&self.key)
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+        let mut changes = Vec::new();
+
+        for event in events {
+            let key = (
+                event.host.clone(),
+                event.service.clone(),
+                event.instance.clone(),
+            );
+
+            let previous = if event.status == Status::OK {
+                firing.remove(&key)
+            } else {
+                firing.insert(key, event.status)
+            };
+
+            if let Some(previous) = previous.filter(|p| *p != event.status) {
+                changes.push(Change::Resolve { event, previous });
+            }
+            if event.status != Status::OK {
+                changes.push(Change::Trigger(event));
+            }
+        }
+
+        (changes, firing)
+    }
+
+    /// Remembers the alerts once their changes were sent,
+    /// a failed send leaves the previous ones to be sent again.
+    pub fn commit(&self, alerts: Alerts) {
+        let mut alerts: Vec<_> = alerts.into_iter().collect();
+        alerts.sort();
+        self.state.set(&self.key, &alerts);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(status: Status) -> Event {
+        Event {
+            host: String::from("zoo1-test"),
+            service: String::from("state"),
+            instance: String::from(""),
+            status,
+            description: String::new(),
+            tags: vec![],
+            metrics: Default::default(),
+            cluster_level: false,
+        }
+    }
+
+    // every change is sent, as a sink does on success
+    fn send<'a>(firing: &Firing, events: &'a [Event]) -> Vec<Change<'a>> {
+        let (changes, alerts) = firing.changes(events);
+        firing.commit(alerts);
+        changes
+    }
+
+    #[test]
+    fn only_open_alerts_should_be_resolved() {
+        let firing = Firing::new("test/firing", Arc::new(State::memory()));
+
+        let ok = [event(Status::OK)];
+        assert!(send(&firing, &ok).is_empty());
+
+        let warn = [event(Status::WARN)];
+        assert_eq!(send(&firing, &warn), vec![Change::Trigger(&warn[0])]);
+        assert_eq!(send(&firing, &warn), vec![Change::Trigger(&warn[0])]);
+
+        let crit = [event(Status::CRIT)];
+        assert_eq!(
+            send(&firing, &crit),
+            vec![
+                Change::Resolve {
+                    event: &crit[0],
+                    previous: Status::WARN
+                },
+                Change::Trigger(&crit[0]),
+            ]
+        );
+
+        assert_eq!(
+            send(&firing, &ok),
+            vec![Change::Resolve {
+                event: &ok[0],
+                previous: Status::CRIT
+            }]
+        );
+        assert!(send(&firing, &ok).is_empty());
+    }
+
+    #[test]
+    fn unsent_changes_should_be_sent_again() {
+        let firing = Firing::new("test/firing", Arc::new(State::memory()));
+        let warn = [event(Status::WARN)];
+        send(&firing, &warn);
+
+        let ok = [event(Status::OK)];
+        let resolve = vec![Change::Resolve {
+            event: &ok[0],
+            previous: Status::WARN,
+        }];
+        // the send failed, nothing is committed
+        assert_eq!(firing.changes(&ok).0, resolve);
+        assert_eq!(send(&firing, &ok), resolve);
+        assert!(send(&firing, &ok).is_empty());
+    }
+}
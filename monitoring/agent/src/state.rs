@@ -0,0 +1,100 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use tracing::warn;
+
+/// What checks and sinks remember between runs, e.g. the last ZooKeeper leader
+/// or the open alerts. Kept in a JSON file so it outlives a restart or a one-shot run.
+#[derive(Debug, Default)]
+pub struct State {
+    path: Option<PathBuf>,
+    values: Mutex<BTreeMap<String, Value>>,
+}
+
+impl State {
+    /// Forgotten when the agent exits.
+    pub fn memory() -> Self {
+        State::default()
+    }
+
+    /// A missing file is an empty state.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let values = match fs::read(path) {
+            Ok(raw) => serde_json::from_slice(&raw)
+                .map_err(|e| format!("failed to parse {}: {e}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(format!("failed to read {}: {e}", path.display())),
+        };
+
+        Ok(State {
+            path: Some(path.to_path_buf()),
+            values: Mutex::new(values),
+        })
+    }
+
+    /// A value that no longer parses, e.g. after an upgrade, is treated as missing.
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let value = self.values.lock().unwrap().get(key)?.clone();
+
+        serde_json::from_value(value)
+            .map_err(|e| warn!("state: ignoring {key}: {e}"))
+            .ok()
+    }
+
+    /// Keeps the value and writes the whole state, a failed write is logged
+    /// and the value is still kept in memory.
+    pub fn set<T: Serialize>(&self, key: &str, value: &T) {
+        let value = match serde_json::to_value(value) {
+            Err(e) => return warn!("state: failed to serialize {key}: {e}"),
+            Ok(value) => value,
+        };
+
+        let mut values = self.values.lock().unwrap();
+        values.insert(key.to_string(), value);
+
+        if let Some(path) = &self.path {
+            if let Err(e) = save(path, &values) {
+                warn!("state: {e}");
+            }
+        }
+    }
+}
+
+// written aside and renamed, so a crash never leaves a partial file
+fn save(path: &Path, values: &BTreeMap<String, Value>) -> Result<(), String> {
+    let raw = serde_json::to_vec_pretty(values).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("tmp");
+
+    fs::write(&tmp, raw).map_err(|e| format!("failed to write {}: {e}", tmp.display()))?;
+    fs::rename(&tmp, path).map_err(|e| format!("failed to write {}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_should_outlive_the_state() {
+        let path = std::env::temp_dir().join(format!("agent-{}-state.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let state = State::open(&path).unwrap();
+        assert_eq!(state.get::<String>("zoo/main/leader"), None);
+        state.set("zoo/main/leader", &"zoo1");
+
+        let reopened = State::open(&path).unwrap();
+        assert_eq!(
+            reopened.get::<String>("zoo/main/leader").as_deref(),
+            Some("zoo1")
+        );
+        // a value of another shape is missing
+        assert_eq!(reopened.get::<u64>("zoo/main/leader"), None);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,352 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use axum::extract::State;
+use axum::response::Html;
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Serialize;
+use tokio::net::TcpListener;
+
+use crate::check::Report;
+use crate::juggler::Status;
+use crate::silence::{self, Silence};
+
+/// Latest result of every host and check, shown by the status page.
+#[derive(Default)]
+pub struct StatusBoard {
+    state: RwLock<BoardState>,
+}
+
+#[derive(Default)]
+struct BoardState {
+    // keyed by (host, service, instance) like Juggler does
+    events: BTreeMap<(String, String, String), EventState>,
+    // keyed by (kind, cluster, host)
+    targets: BTreeMap<(String, String, String), TargetState>,
+}
+
+struct EventState {
+    kind: String,
+    cluster: String,
+    status: Status,
+    description: String,
+    silenced: bool,
+    updated: SystemTime,
+}
+
+struct TargetState {
+    latency: Duration,
+    last_success: Option<SystemTime>,
+    last_error: Option<String>,
+}
+
+/// One line of the status page and of `/api/status`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Row {
+    pub host: String,
+    pub kind: String,
+    pub cluster: String,
+    pub service: String,
+    pub instance: String,
+    /// Unset until the host has been collected once.
+    pub status: Option<Status>,
+    pub description: String,
+    /// A silence matched the event. It is shown downgraded as sent to Juggler,
+    /// or with its collected status when the silence drops it.
+    pub silenced: bool,
+    /// Unix time of the last successful collection.
+    pub last_success: Option<u64>,
+    pub latency_ms: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+impl StatusBoard {
+    /// Events are shown as the alerting sinks get them after the `silences`.
+    pub fn update(&self, reports: &[Report], silences: &[Silence]) {
+        let now = SystemTime::now();
+        let mut state = self.state.write().unwrap();
+
+        for report in reports {
+            for target in &report.targets {
+                let key = (
+                    report.kind.to_string(),
+                    report.cluster.clone(),
+                    target.host.clone(),
+                );
+                let entry = state.targets.entry(key).or_insert(TargetState {
+                    latency: target.latency,
+                    last_success: None,
+                    last_error: None,
+                });
+
+                entry.latency = target.latency;
+                entry.last_error = target.error.clone();
+                if target.error.is_none() {
+                    entry.last_success = Some(now);
+                }
+            }
+
+            for event in &report.events {
+                let silenced = silences.iter().any(|s| s.matches(event));
+                let event = silence::apply(vec![event.clone()], silences)
+                    .pop()
+                    .unwrap_or_else(|| event.clone());
+
+                state.events.insert(
+                    (
+                        event.host.clone(),
+                        event.service.clone(),
+                        event.instance.clone(),
+                    ),
+                    EventState {
+                        kind: report.kind.to_string(),
+                        cluster: report.cluster.clone(),
+                        status: event.status,
+                        description: event.description.clone(),
+                        silenced,
+                        updated: now,
+                    },
+                );
+            }
+        }
+    }
+
+    pub fn rows(&self) -> Vec<Row> {
+        let state = self.state.read().unwrap();
+        let mut rows = Vec::new();
+        let mut seen = BTreeSet::new();
+
+        for ((host, service, instance), event) in &state.events {
+            let key = (event.kind.clone(), event.cluster.clone(), host.clone());
+            let target = state.targets.get(&key);
+            seen.insert(key);
+
+            rows.push(Row {
+                host: host.clone(),
+                kind: event.kind.clone(),
+                cluster: event.cluster.clone(),
+                service: service.clone(),
+                instance: instance.clone(),
+                status: Some(event.status),
+                description: event.description.clone(),
+                silenced: event.silenced,
+                // events not tied to a target (e.g. shovels) were collected when updated
+                last_success: match target {
+                    Some(target) => target.last_success.map(unix),
+                    None => Some(unix(event.updated)),
+                },
+                latency_ms: target.map(|t| t.latency.as_millis() as u64),
+                last_error: target.and_then(|t| t.last_error.clone()),
+            });
+        }
+
+        // targets that never produced an event
+        for ((kind, cluster, host), target) in &state.targets {
+            if seen.contains(&(kind.clone(), cluster.clone(), host.clone())) {
+                continue;
+            }
+
+            rows.push(Row {
+                host: host.clone(),
+                kind: kind.clone(),
+                cluster: cluster.clone(),
+                service: String::new(),
+                instance: String::new(),
+                status: None,
+                description: String::new(),
+                silenced: false,
+                last_success: target.last_success.map(unix),
+                latency_ms: Some(target.latency.as_millis() as u64),
+                last_error: target.last_error.clone(),
+            });
+        }
+
+        rows
+    }
+}
+
+fn unix(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn render(rows: &[Row]) -> String {
+    let now = unix(SystemTime::now());
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>agent status</title></head>\n<body>\n\
+         <table border=\"1\">\n<tr><th>host</th><th>check</th><th>service</th><th>status</th>\
+         <th>description</th><th>last success</th><th>latency</th><th>last error</th></tr>\n",
+    );
+
+    for row in rows {
+        let status = match row.status {
+            Some(status) if row.silenced => format!("{status:?} (silenced)"),
+            Some(status) => format!("{status:?}"),
+            None => String::from("-"),
+        };
+        let last_success = match row.last_success {
+            Some(at) => format!("{}s ago", now.saturating_sub(at)),
+            None => String::from("never"),
+        };
+        let latency = match row.latency_ms {
+            Some(latency) => format!("{latency}ms"),
+            None => String::from("-"),
+        };
+        let service = if row.instance.is_empty() {
+            row.service.clone()
+        } else {
+            format!("{}/{}", row.service, row.instance)
+        };
+
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}/{}</td><td>{}</td><td>{}</td><td><pre>{}</pre></td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            escape(&row.host),
+            row.kind,
+            escape(&row.cluster),
+            escape(&service),
+            status,
+            escape(&row.description),
+            last_success,
+            latency,
+            escape(row.last_error.as_deref().unwrap_or_default()),
+        ));
+    }
+
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+async fn page(State(board): State<Arc<StatusBoard>>) -> Html<String> {
+    Html(render(&board.rows()))
+}
+
+async fn api(State(board): State<Arc<StatusBoard>>) -> Json<Vec<Row>> {
+    Json(board.rows())
+}
+
+pub fn router(board: Arc<StatusBoard>) -> Router {
+    Router::new()
+        .route("/", get(page))
+        .route("/api/status", get(api))
+        .with_state(board)
+}
+
+/// Serves the status page until the listener fails.
+pub async fn serve(listener: TcpListener, board: Arc<StatusBoard>) -> std::io::Result<()> {
+    axum::serve(listener, router(board)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::OffsetDateTime;
+
+    use crate::check::TargetResult;
+    use crate::juggler::Event;
+    use crate::silence::Action;
+
+    fn report(error: Option<&str>, events: Vec<Event>) -> Report {
+        Report {
+            kind: "zoo",
+            cluster: String::from("main"),
+            targets: vec![TargetResult {
+                target: String::from("zoo1.example.com:2181"),
+                host: String::from("zoo1-test"),
+                latency: Duration::from_millis(12),
+                error: error.map(str::to_string),
+            }],
+            events,
+        }
+    }
+
+    fn event(status: Status) -> Event {
+        Event {
+            host: String::from("zoo1-test"),
+            service: String::from("state"),
+            instance: String::from(""),
+            status,
+            description: String::from("leader <3>"),
+            tags: vec![],
+            metrics: Default::default(),
+            cluster_level: false,
+        }
+    }
+
+    #[test]
+    fn failed_collection_should_keep_last_result() {
+        let board = StatusBoard::default();
+
+        board.update(&[report(None, vec![event(Status::OK)])], &[]);
+        board.update(&[report(Some("connection refused"), vec![])], &[]);
+
+        let rows = board.rows();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].status, Some(Status::OK));
+        assert_eq!(rows[0].latency_ms, Some(12));
+        assert!(rows[0].last_success.is_some());
+        assert_eq!(rows[0].last_error.as_deref(), Some("connection refused"));
+    }
+
+    #[test]
+    fn never_collected_target_should_be_listed() {
+        let board = StatusBoard::default();
+
+        board.update(&[report(Some("timed out"), vec![])], &[]);
+
+        let rows = board.rows();
+        assert_eq!(rows[0].host, "zoo1-test");
+        assert_eq!(rows[0].status, None);
+        assert_eq!(rows[0].last_success, None);
+    }
+
+    #[test]
+    fn page_should_be_escaped() {
+        let board = StatusBoard::default();
+        board.update(&[report(None, vec![event(Status::CRIT)])], &[]);
+
+        let html = render(&board.rows());
+
+        assert!(html.contains("<td>CRIT</td>"));
+        assert!(html.contains("leader &lt;3&gt;"));
+    }
+
+    #[test]
+    fn silenced_events_should_be_shown_as_sent() {
+        let board = StatusBoard::default();
+        let silence = |action| Silence {
+            host: String::from("zoo*"),
+            service: String::from("*"),
+            starts: OffsetDateTime::UNIX_EPOCH,
+            ends: OffsetDateTime::UNIX_EPOCH + time::Duration::hours(1),
+            reason: None,
+            action,
+        };
+
+        board.update(
+            &[report(None, vec![event(Status::CRIT)])],
+            &[silence(Action::Downgrade)],
+        );
+        let rows = board.rows();
+        assert_eq!(rows[0].status, Some(Status::OK));
+        assert!(rows[0].silenced);
+        assert!(rows[0].description.starts_with("silenced until "));
+        assert!(render(&rows).contains("<td>OK (silenced)</td>"));
+
+        // nothing was sent, so the collected status is shown
+        board.update(
+            &[report(None, vec![event(Status::CRIT)])],
+            &[silence(Action::Drop)],
+        );
+        let rows = board.rows();
+        assert_eq!(rows[0].status, Some(Status::CRIT));
+        assert!(rows[0].silenced);
+    }
+}
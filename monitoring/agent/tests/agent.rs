@@ -0,0 +1,494 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::ClientBuilder;
+
+use agent::agent::Agent;
+use agent::checks;
+use agent::config::Config;
+use agent::history::{Filter, History};
+use agent::juggler::Juggler;
+use agent::meta::{Heartbeat, SelfMonitoring};
+use agent::silence::Silences;
+use agent::sink::Sink;
+use agent::state::State;
+use testkit::{transcripts, FakeHttp, FakeZoo, Mode, RunningHttp};
+
+const SHOVELS: &str = r#"[
+    {"node": "rabbit@rmq1.example.com", "name": "orders", "vhost": "/", "type": "dynamic", "state": "terminated", "reason": "econnrefused"}
+]"#;
+
+#[tokio::test]
+async fn checks_of_every_kind_should_share_one_juggler_payload() {
+    let leader = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_LEADER)
+        .start()
+        .await;
+    let follower = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_FOLLOWER)
+        .start()
+        .await;
+    let broken = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_FOLLOWER)
+        .mode(Mode::Malformed)
+        .start()
+        .await;
+    let standalone = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_LEADER_DEGRADED)
+        .start()
+        .await;
+    let broker = FakeHttp::new()
+        .basic_auth("guest", "guest")
+        .get("/api/shovels", 200, SHOVELS)
+        .start()
+        .await;
+    let juggler = FakeHttp::new()
+        .post("/events", 200, r#"{"success":true}"#)
+        .start()
+        .await;
+
+    let config: Config = toml::from_str(&format!(
+        r#"
+        [juggler]
+        url = "{}"
+        source = "test"
+
+        [[zoo]]
+        cluster = "main"
+        hosts = ["{}", "{}", "{}"]
+
+        [[zoo]]
+        cluster = "billing"
+        hosts = ["{}", "127.0.0.1:1"]
+
+        [zoo.naming]
+        strategy = "alias"
+        aliases = {{ "{}" = "zoo-billing" }}
+
+        [[rmq]]
+        cluster = "shovels"
+        hosts = ["{}"]
+        port = {}
+        federation = false
+
+        [rmq.credentials]
+        login = "guest"
+        password = "guest"
+        "#,
+        juggler.url(),
+        leader.addr(),
+        follower.addr(),
+        broken.addr(),
+        standalone.addr(),
+        standalone.addr(),
+        broker.host(),
+        broker.port(),
+    ))
+    .unwrap();
+
+    let checks = checks::registry(&config, &Arc::new(State::memory())).unwrap();
+    let client = ClientBuilder::new().build().unwrap();
+    let sinks: Vec<Box<dyn Sink>> = vec![Box::new(Juggler::new(
+        client,
+        config.juggler.url.clone(),
+        config.juggler.source.clone(),
+    ))];
+
+    let reports = Agent::new(checks, sinks).run_once().await.unwrap();
+
+    assert_eq!(reports.len(), 3);
+    assert_eq!(reports[0].cluster, "main");
+    assert_eq!(reports[0].events.len(), 4);
+    assert!(reports[0].targets[2].error.is_some());
+    assert_eq!(reports[1].cluster, "billing");
+    assert_eq!(reports[1].events.len(), 3);
+    assert!(reports[1].targets[1].error.is_some());
+    assert_eq!(reports[2].kind, "rmq");
+
+    let requests = juggler.requests();
+    assert_eq!(requests.len(), 1);
+
+    let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+    let events = body["events"].as_array().unwrap();
+
+    assert_eq!(body["source"], "test");
+    assert_eq!(events.len(), 8);
+    assert_eq!(events[0]["host"], "127.0.0.1-test");
+    assert_eq!(events[0]["description"], "leader. followers: 2/2");
+    // the cluster name is not a host to rename
+    assert_eq!(events[2]["host"], "main");
+    assert_eq!(events[2]["service"], "leader-election");
+    // a member answering garbage while its ensemble is up
+    assert_eq!(events[3]["service"], "state");
+    assert_eq!(events[3]["status"], "WARN");
+    assert!(events[3]["description"]
+        .as_str()
+        .unwrap()
+        .starts_with("failed to parse reply of 127.0.0.1:"));
+    assert_eq!(events[4]["host"], "zoo-billing");
+    assert_eq!(events[4]["description"], "leader. followers: 1/1");
+    // and one that is down
+    assert_eq!(events[6]["status"], "CRIT");
+    assert!(events[6]["description"]
+        .as_str()
+        .unwrap()
+        .starts_with("failed to connect to 127.0.0.1:1: "));
+    // rmq1.example.com is not polled, the shovel goes to the broker that reported it
+    assert_eq!(events[7]["host"], "127.0.0.1-test");
+    assert_eq!(events[7]["service"], "shovel");
+    assert_eq!(events[7]["status"], "CRIT");
+}
+
+#[tokio::test]
+async fn nothing_collected_should_fail_the_run() {
+    let juggler = FakeHttp::new()
+        .post("/events", 200, r#"{"success":true}"#)
+        .start()
+        .await;
+
+    let config: Config = toml::from_str(&format!(
+        r#"
+        [juggler]
+        url = "{}"
+
+        [[zoo]]
+        cluster = "main"
+        hosts = ["127.0.0.1:1"]
+        "#,
+        juggler.url(),
+    ))
+    .unwrap();
+
+    let checks = checks::registry(&config, &Arc::new(State::memory())).unwrap();
+    let client = ClientBuilder::new().build().unwrap();
+    let sinks: Vec<Box<dyn Sink>> = vec![Box::new(Juggler::new(
+        client,
+        config.juggler.url.clone(),
+        config.juggler.source.clone(),
+    ))];
+
+    let error = Agent::new(checks, sinks).run_once().await.unwrap_err();
+    assert_eq!(error.to_string(), "no target could be collected");
+
+    // the member that is down is still reported
+    let requests = juggler.requests();
+    assert_eq!(requests.len(), 1);
+
+    let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+    let events = body["events"].as_array().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["service"], "state");
+    assert_eq!(events[0]["status"], "CRIT");
+    assert!(events[0]["description"]
+        .as_str()
+        .unwrap()
+        .starts_with("failed to connect to 127.0.0.1:1: "));
+}
+
+async fn election(state: &Path, hosts: [String; 2], juggler: &RunningHttp) -> String {
+    let config: Config = toml::from_str(&format!(
+        r#"
+        [juggler]
+        url = "{}"
+
+        [[zoo]]
+        cluster = "main"
+        hosts = {hosts:?}
+        "#,
+        juggler.url(),
+    ))
+    .unwrap();
+
+    // a fresh agent as a one-shot run would start it
+    let state = Arc::new(State::open(state).unwrap());
+    let checks = checks::registry(&config, &state).unwrap();
+    let client = ClientBuilder::new().build().unwrap();
+    let sinks: Vec<Box<dyn Sink>> = vec![Box::new(Juggler::new(
+        client,
+        config.juggler.url.clone(),
+        config.juggler.source.clone(),
+    ))];
+
+    let reports = Agent::new(checks, sinks).run_once().await.unwrap();
+    let event = reports[0]
+        .events
+        .iter()
+        .find(|e| e.service == "leader-election")
+        .unwrap();
+    event.description.clone()
+}
+
+#[tokio::test]
+async fn leader_change_should_be_seen_across_restarts() {
+    let state = std::env::temp_dir().join(format!("agent-{}-election.json", std::process::id()));
+    let _ = fs::remove_file(&state);
+    let leader = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_LEADER)
+        .start()
+        .await;
+    let new_leader = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_LEADER)
+        .start()
+        .await;
+    let follower = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_FOLLOWER)
+        .start()
+        .await;
+    let juggler = FakeHttp::new()
+        .post("/events", 200, r#"{"success":true}"#)
+        .start()
+        .await;
+
+    let first = election(&state, [leader.addr(), follower.addr()], &juggler).await;
+    let second = election(&state, [new_leader.addr(), follower.addr()], &juggler).await;
+
+    assert!(first.starts_with(&format!("leader: {}", leader.addr())));
+    assert!(second.starts_with(&format!(
+        "leader changed: {} -> {}, leader changes in the last 3600s: 1/",
+        leader.addr(),
+        new_leader.addr()
+    )));
+
+    fs::remove_file(&state).unwrap();
+}
+
+#[tokio::test]
+async fn silenced_events_should_be_downgraded() {
+    let standalone = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_LEADER_DEGRADED)
+        .start()
+        .await;
+    let juggler = FakeHttp::new()
+        .post("/events", 200, r#"{"success":true}"#)
+        .start()
+        .await;
+
+    let config: Config = toml::from_str(&format!(
+        r#"
+        [juggler]
+        url = "{}"
+
+        [[zoo]]
+        cluster = "main"
+        hosts = ["{}"]
+        expected_followers = 2
+
+        [[silence]]
+        host = "127.*"
+        service = "state"
+        starts = "2020-01-01T00:00:00Z"
+        ends = "2999-01-01T00:00:00Z"
+        reason = "zoo upgrade"
+
+        [[silence]]
+        service = "leader-election"
+        starts = "2020-01-01T00:00:00Z"
+        ends = "2999-01-01T00:00:00Z"
+        action = "drop"
+        "#,
+        juggler.url(),
+        standalone.addr(),
+    ))
+    .unwrap();
+
+    let path = std::env::temp_dir().join(format!("agent-{}-silenced.db", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let retention = Duration::from_secs(3600);
+
+    let checks = checks::registry(&config, &Arc::new(State::memory())).unwrap();
+    let client = ClientBuilder::new().build().unwrap();
+    let sinks: Vec<Box<dyn Sink>> = vec![
+        Box::new(Juggler::new(
+            client,
+            config.juggler.url.clone(),
+            config.juggler.source.clone(),
+        )),
+        Box::new(History::open(&path, retention).await.unwrap()),
+    ];
+    let silences = Silences::new(config.silence.clone(), None);
+
+    Agent::new(checks, sinks)
+        .with_silences(silences)
+        .run_once()
+        .await
+        .unwrap();
+
+    let requests = juggler.requests();
+    let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+    let events = body["events"].as_array().unwrap();
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["status"], "OK");
+    assert_eq!(
+        events[0]["description"],
+        "silenced until 2999-01-01T00:00:00Z: zoo upgrade, was WARN\nleader. followers: 1/2"
+    );
+
+    // the history keeps what was collected
+    let history = History::open(&path, retention).await.unwrap();
+    let records = history.query(&Filter::default()).await.unwrap();
+
+    assert_eq!(records.len(), 2);
+    assert!(records.iter().all(|r| r.silenced));
+    assert_eq!(records[0].service, "state");
+    assert_eq!(records[0].status, "WARN");
+    assert_eq!(records[0].description, "leader. followers: 1/2");
+    assert_eq!(records[1].service, "leader-election");
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn agent_should_report_itself_and_beat() {
+    let leader = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_LEADER_DEGRADED)
+        .start()
+        .await;
+    let juggler = FakeHttp::new()
+        .post("/events", 200, r#"{"success":true}"#)
+        .start()
+        .await;
+    let heartbeat = FakeHttp::new().get("/ping/agent", 200, "OK").start().await;
+
+    let agent = |hosts: &str| {
+        let config: Config = toml::from_str(&format!(
+            r#"
+            [juggler]
+            url = "{}"
+
+            [[zoo]]
+            cluster = "main"
+            hosts = [{hosts}]
+            "#,
+            juggler.url(),
+        ))
+        .unwrap();
+
+        let checks = checks::registry(&config, &Arc::new(State::memory())).unwrap();
+        let client = ClientBuilder::new().build().unwrap();
+        let sinks: Vec<Box<dyn Sink>> = vec![Box::new(Juggler::new(
+            client.clone(),
+            config.juggler.url.clone(),
+            config.juggler.source.clone(),
+        ))];
+
+        Agent::new(checks, sinks)
+            .with_self_monitoring(SelfMonitoring::new(
+                String::from("agent-test"),
+                String::from("v1"),
+            ))
+            .with_heartbeat(Heartbeat::new(
+                client,
+                format!("{}/ping/agent", heartbeat.url()),
+            ))
+    };
+
+    agent(&format!(r#""{}", "127.0.0.1:1""#, leader.addr()))
+        .run_once()
+        .await
+        .unwrap();
+
+    // the event about the agent itself is sent even when nothing else was collected
+    assert!(agent(r#""127.0.0.1:1""#).run_once().await.is_err());
+
+    let requests = juggler.requests();
+    assert_eq!(requests.len(), 2);
+
+    let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+    let meta = body["events"].as_array().unwrap().last().unwrap().clone();
+    assert_eq!(meta["host"], "agent-test");
+    assert_eq!(meta["service"], "monitoring-self");
+    assert_eq!(meta["status"], "WARN");
+    assert!(meta["description"]
+        .as_str()
+        .unwrap()
+        .starts_with("hosts succeeded: 1/2 in "));
+
+    // the member that is down and the agent itself
+    let body: serde_json::Value = serde_json::from_str(&requests[1].body).unwrap();
+    let events = body["events"].as_array().unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0]["service"], "state");
+    assert_eq!(events[0]["status"], "CRIT");
+    assert_eq!(events[1]["service"], "monitoring-self");
+    assert_eq!(events[1]["status"], "CRIT");
+
+    // only the successful run pinged the heartbeat
+    assert_eq!(heartbeat.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn events_should_carry_cluster_enrichment() {
+    let standalone = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_LEADER_DEGRADED)
+        .start()
+        .await;
+    let juggler = FakeHttp::new()
+        .post("/events", 200, r#"{"success":true}"#)
+        .start()
+        .await;
+
+    let config: Config = toml::from_str(&format!(
+        r#"
+        [juggler]
+        url = "{}"
+
+        [enrichment.zoo]
+        runbook = "https://wiki.example.com/zookeeper"
+        team = "storage"
+        descriptions = {{ state = "{{description}}, {{zk_znode_count}} znodes" }}
+
+        [[zoo]]
+        cluster = "main"
+        hosts = ["{}"]
+        expected_followers = 2
+
+        [zoo.enrichment]
+        environment = "production"
+        "#,
+        juggler.url(),
+        standalone.addr(),
+    ))
+    .unwrap();
+
+    let checks = checks::registry(&config, &Arc::new(State::memory())).unwrap();
+    let client = ClientBuilder::new().build().unwrap();
+    let sinks: Vec<Box<dyn Sink>> = vec![Box::new(Juggler::new(
+        client,
+        config.juggler.url.clone(),
+        config.juggler.source.clone(),
+    ))];
+
+    Agent::new(checks, sinks).run_once().await.unwrap();
+
+    let requests = juggler.requests();
+    let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+    let events = body["events"].as_array().unwrap();
+
+    assert_eq!(events[0]["host"], "127.0.0.1-test");
+    assert_eq!(
+        events[0]["description"],
+        "leader. followers: 1/2, 5 znodes\n\
+         cluster: main, team: storage, environment: production\n\
+         runbook: https://wiki.example.com/zookeeper"
+    );
+    assert_eq!(
+        events[0]["tags"],
+        serde_json::json!([
+            "zoo",
+            "k8s",
+            "monitoring",
+            "cluster:main",
+            "team:storage",
+            "env:production"
+        ])
+    );
+    // no template for this service
+    assert_eq!(events[1]["service"], "leader-election");
+    assert!(events[1]["description"]
+        .as_str()
+        .unwrap()
+        .ends_with("runbook: https://wiki.example.com/zookeeper"));
+}
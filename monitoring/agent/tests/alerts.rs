@@ -0,0 +1,153 @@
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::ClientBuilder;
+
+use agent::alertmanager::Alertmanager;
+use agent::config::PagerDutyConfig;
+use agent::juggler::{Event, Status};
+use agent::pagerduty::PagerDuty;
+use agent::sink::Sink;
+use agent::state::State;
+use testkit::FakeHttp;
+
+fn event(service: &str, status: Status) -> Event {
+    Event {
+        host: String::from("zoo1-test"),
+        service: service.to_string(),
+        instance: String::from(""),
+        status,
+        description: String::from("leader. followers: 1/2"),
+        tags: vec![String::from("zoo")],
+        metrics: Default::default(),
+        cluster_level: false,
+    }
+}
+
+fn body(request: &testkit::Request) -> serde_json::Value {
+    serde_json::from_str(&request.body).unwrap()
+}
+
+#[tokio::test]
+async fn alertmanager_should_fire_and_resolve() {
+    let alertmanager = FakeHttp::new().post("/api/v2/alerts", 200, "").start().await;
+    let sink = Alertmanager::new(
+        ClientBuilder::new().build().unwrap(),
+        alertmanager.url(),
+        String::from("agent"),
+        Duration::from_secs(300),
+    );
+
+    // nothing is open yet, so OK events are not sent
+    sink.send(&[event("state", Status::OK)]).await.unwrap();
+    sink.send(&[event("state", Status::WARN), event("leader-election", Status::OK)])
+        .await
+        .unwrap();
+    sink.send(&[event("state", Status::OK)]).await.unwrap();
+
+    let requests = alertmanager.requests();
+    assert_eq!(requests.len(), 2);
+
+    let firing = body(&requests[0]);
+    assert_eq!(firing.as_array().unwrap().len(), 1);
+    assert_eq!(firing[0]["labels"]["alertname"], "state");
+    assert_eq!(firing[0]["labels"]["severity"], "warning");
+    assert_ne!(firing[0]["startsAt"], firing[0]["endsAt"]);
+
+    let resolved = body(&requests[1]);
+    assert_eq!(resolved[0]["labels"], firing[0]["labels"]);
+    assert_eq!(resolved[0]["startsAt"], resolved[0]["endsAt"]);
+}
+
+#[tokio::test]
+async fn alert_should_be_resolved_after_a_restart() {
+    let path = std::env::temp_dir().join(format!("agent-{}-firing.json", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let alertmanager = FakeHttp::new().post("/api/v2/alerts", 200, "").start().await;
+    // every one-shot run starts a fresh sink
+    let sink = || {
+        Alertmanager::new(
+            ClientBuilder::new().build().unwrap(),
+            alertmanager.url(),
+            String::from("agent"),
+            Duration::from_secs(300),
+        )
+        .with_state(Arc::new(State::open(&path).unwrap()))
+    };
+
+    sink().send(&[event("state", Status::CRIT)]).await.unwrap();
+    sink().send(&[event("state", Status::OK)]).await.unwrap();
+
+    let requests = alertmanager.requests();
+    assert_eq!(requests.len(), 2);
+
+    let resolved = body(&requests[1]);
+    assert_eq!(resolved[0]["labels"]["severity"], "critical");
+    assert_eq!(resolved[0]["startsAt"], resolved[0]["endsAt"]);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn pagerduty_should_trigger_and_resolve() {
+    let pagerduty = FakeHttp::new()
+        .post("/v2/enqueue", 202, r#"{"status":"success"}"#)
+        .start()
+        .await;
+    let config: PagerDutyConfig = toml::from_str(&format!(
+        r#"
+        url = "{}"
+        routing_key = "R0UT1NG"
+        "#,
+        pagerduty.url()
+    ))
+    .unwrap();
+    let sink = PagerDuty::new(
+        ClientBuilder::new().build().unwrap(),
+        config.url.clone(),
+        config.routing_key().unwrap(),
+        String::from("agent"),
+    );
+
+    sink.send(&[event("state", Status::WARN)]).await.unwrap();
+    sink.send(&[event("state", Status::CRIT)]).await.unwrap();
+    sink.send(&[event("state", Status::OK)]).await.unwrap();
+
+    let requests: Vec<serde_json::Value> = pagerduty.requests().iter().map(body).collect();
+    assert_eq!(requests.len(), 3);
+
+    assert_eq!(requests[0]["event_action"], "trigger");
+    assert_eq!(requests[0]["routing_key"], "R0UT1NG");
+    assert_eq!(requests[0]["payload"]["severity"], "warning");
+    assert_eq!(requests[1]["payload"]["severity"], "critical");
+    assert_eq!(requests[1]["dedup_key"], requests[0]["dedup_key"]);
+
+    assert_eq!(requests[2]["event_action"], "resolve");
+    assert_eq!(requests[2]["dedup_key"], "agent/zoo1-test/state/");
+    assert!(requests[2].get("payload").is_none());
+}
+
+#[tokio::test]
+async fn rejected_pagerduty_events_should_fail_the_sink() {
+    let pagerduty = FakeHttp::new()
+        .post("/v2/enqueue", 400, r#"{"status":"invalid event"}"#)
+        .start()
+        .await;
+    let sink = PagerDuty::new(
+        ClientBuilder::new().build().unwrap(),
+        pagerduty.url(),
+        toml::from_str::<PagerDutyConfig>(r#"routing_key = "R0UT1NG""#)
+            .unwrap()
+            .routing_key()
+            .unwrap(),
+        String::from("agent"),
+    );
+
+    let error = sink
+        .send(&[event("state", Status::CRIT)])
+        .await
+        .unwrap_err();
+
+    assert_eq!(error.to_string(), "1/1 events were not accepted");
+}
@@ -0,0 +1,111 @@
+use agent::check::{DynCheck, Report};
+use agent::checks::zoo::ZooCheck;
+use agent::config::ZooConfig;
+use testkit::{transcripts, FakeDns, FakeZoo};
+
+const NAME: &str = "zookeeper.zoo.svc.cluster.local";
+
+fn targets(report: &Report) -> Vec<&str> {
+    report.targets.iter().map(|t| t.target.as_str()).collect()
+}
+
+fn leader_description(report: &Report) -> &str {
+    report
+        .events
+        .iter()
+        .find(|e| e.description.starts_with("leader."))
+        .map(|e| e.description.as_str())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn members_should_follow_a_records_between_runs() {
+    let leader = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_LEADER)
+        .start()
+        .await;
+    let port = leader.addr().rsplit_once(':').unwrap().1.to_string();
+    let dns = FakeDns::new().ips(NAME, &["127.0.0.1"]).start().await;
+
+    let config: ZooConfig = toml::from_str(&format!(
+        r#"
+        cluster = "main"
+
+        [discovery]
+        name = "{NAME}"
+        port = {port}
+        resolver = "{}"
+        "#,
+        dns.addr()
+    ))
+    .unwrap();
+    let check = ZooCheck::new(&config).unwrap();
+
+    let report = check.run().await;
+    assert_eq!(targets(&report), vec![leader.addr()]);
+    assert_eq!(leader_description(&report), "leader. followers: 2/0");
+
+    // two more members were scheduled, they do not answer yet
+    dns.set_ips(NAME, &["127.0.0.1", "127.0.0.2", "127.0.0.3"]);
+    let report = check.run().await;
+    assert_eq!(
+        targets(&report),
+        vec![
+            leader.addr(),
+            format!("127.0.0.2:{port}"),
+            format!("127.0.0.3:{port}"),
+        ]
+    );
+    assert_eq!(leader_description(&report), "leader. followers: 2/2");
+
+    // an empty answer keeps the members of the previous run
+    dns.set_ips(NAME, &[]);
+    let report = check.run().await;
+    assert_eq!(report.targets.len(), 3);
+}
+
+#[tokio::test]
+async fn members_should_be_read_from_srv_records() {
+    let leader = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_LEADER)
+        .start()
+        .await;
+    let follower = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_FOLLOWER)
+        .start()
+        .await;
+    let port = |addr: String| addr.rsplit_once(':').unwrap().1.parse::<u16>().unwrap();
+
+    let srv = "_client._tcp.zookeeper.zoo.svc.cluster.local";
+    let dns = FakeDns::new()
+        .srv(
+            srv,
+            &[
+                ("127.0.0.1", port(leader.addr())),
+                ("127.0.0.1", port(follower.addr())),
+            ],
+        )
+        .start()
+        .await;
+
+    let config: ZooConfig = toml::from_str(&format!(
+        r#"
+        cluster = "main"
+
+        [discovery]
+        record = "srv"
+        name = "{srv}"
+        resolver = "{}"
+        "#,
+        dns.addr()
+    ))
+    .unwrap();
+
+    let report = ZooCheck::new(&config).unwrap().run().await;
+
+    let mut expected = vec![leader.addr(), follower.addr()];
+    expected.sort();
+    assert_eq!(targets(&report), expected);
+    assert!(report.targets.iter().all(|t| t.error.is_none()));
+    assert_eq!(leader_description(&report), "leader. followers: 2/1");
+}
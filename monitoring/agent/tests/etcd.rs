@@ -0,0 +1,115 @@
+use agent::check::DynCheck;
+use agent::checks::etcd::EtcdCheck;
+use agent::config::EtcdConfig;
+use agent::juggler::Status;
+use testkit::{FakeHttp, RunningHttp};
+
+async fn member(member_id: u64, leader: u64, raft_term: u64, db_size: u64) -> RunningHttp {
+    FakeHttp::new()
+        .get("/health", 200, r#"{"health":"true","reason":""}"#)
+        .post(
+            "/v3/maintenance/status",
+            200,
+            &format!(
+                r#"{{"header":{{"member_id":"{member_id}","raft_term":"{raft_term}"}},"version":"3.5.9","dbSize":"{db_size}","leader":"{leader}","raftTerm":"{raft_term}"}}"#
+            ),
+        )
+        .start()
+        .await
+}
+
+fn check(members: &[&RunningHttp]) -> EtcdCheck {
+    EtcdCheck::new(&EtcdConfig {
+        cluster: String::from("k8s"),
+        hosts: members
+            .iter()
+            .map(|m| format!("{}:{}", m.host(), m.port()))
+            .collect(),
+        discovery: None,
+        scheme: String::from("http"),
+        quota_bytes: 1000,
+        db_warn: 0.8,
+        naming: None,
+        enrichment: None,
+    })
+    .unwrap()
+}
+
+#[tokio::test]
+async fn healthy_cluster_should_agree_on_leader() {
+    let m1 = member(1, 1, 5, 100).await;
+    let m2 = member(2, 1, 5, 100).await;
+    let m3 = member(3, 1, 5, 900).await;
+
+    let report = check(&[&m1, &m2, &m3]).run().await;
+
+    assert!(report.targets.iter().all(|t| t.error.is_none()));
+    assert_eq!(report.events.len(), 6);
+
+    assert_eq!(report.events[0].service, "etcd-state");
+    assert_eq!(report.events[0].status, Status::OK);
+    assert_eq!(report.events[0].description, "leader. members: 3/3, term: 5");
+    assert_eq!(report.events[2].description, "follower, term: 5");
+
+    assert_eq!(report.events[5].service, "etcd-db");
+    assert_eq!(report.events[5].status, Status::WARN);
+    assert_eq!(report.events[5].description, "db size: 900/1000 bytes (90%)");
+}
+
+#[tokio::test]
+async fn split_brain_should_be_critical() {
+    let m1 = member(1, 1, 5, 100).await;
+    let m2 = member(2, 2, 6, 100).await;
+
+    let report = check(&[&m1, &m2]).run().await;
+
+    assert!(report
+        .events
+        .iter()
+        .filter(|e| e.service == "etcd-state")
+        .all(|e| e.status == Status::CRIT));
+    assert!(report.events[0]
+        .description
+        .contains("members disagree on the leader: {1, 2}"));
+}
+
+#[tokio::test]
+async fn unhealthy_member_should_be_critical() {
+    let m1 = FakeHttp::new()
+        .get("/health", 503, r#"{"health":"false","reason":"RAFT NO LEADER"}"#)
+        .post(
+            "/v3/maintenance/status",
+            200,
+            r#"{"header":{"member_id":"1"},"version":"3.5.9","dbSize":"100","leader":"0","raftTerm":"5"}"#,
+        )
+        .start()
+        .await;
+
+    let report = check(&[&m1]).run().await;
+
+    assert_eq!(report.events[0].status, Status::CRIT);
+    assert_eq!(
+        report.events[0].description,
+        "follower, term: 5\nunhealthy: RAFT NO LEADER\nno leader"
+    );
+}
+
+#[tokio::test]
+async fn member_without_leader_should_be_critical() {
+    // zero values are left out of the JSON gateway replies
+    let m1 = FakeHttp::new()
+        .get("/health", 200, r#"{"health":"true","reason":""}"#)
+        .post(
+            "/v3/maintenance/status",
+            200,
+            r#"{"header":{"member_id":"1"},"version":"3.5.9","dbSize":"100","raftTerm":"5"}"#,
+        )
+        .start()
+        .await;
+
+    let report = check(&[&m1]).run().await;
+
+    assert!(report.targets[0].error.is_none());
+    assert_eq!(report.events[0].status, Status::CRIT);
+    assert_eq!(report.events[0].description, "follower, term: 5\nno leader");
+}
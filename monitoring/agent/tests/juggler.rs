@@ -0,0 +1,87 @@
+use reqwest::ClientBuilder;
+
+use agent::config::JugglerConfig;
+use agent::juggler::{Event, Juggler, Status};
+use agent::sink::Sink;
+use testkit::FakeHttp;
+
+fn events(n: usize) -> Vec<Event> {
+    (0..n)
+        .map(|i| Event {
+            host: format!("rmq{i}-test"),
+            service: String::from("shovel"),
+            instance: String::from("orders"),
+            status: Status::OK,
+            description: String::from("running"),
+            tags: vec![],
+            metrics: Default::default(),
+            cluster_level: false,
+        })
+        .collect()
+}
+
+fn juggler(url: String) -> Juggler {
+    let config: JugglerConfig = toml::from_str(&format!(
+        r#"
+        url = "{url}"
+        source = "test"
+        max_events = 2
+        concurrency = 2
+        "#
+    ))
+    .unwrap();
+
+    Juggler::new(
+        ClientBuilder::new().build().unwrap(),
+        config.url,
+        config.source,
+    )
+    .with_batching(config.batching)
+}
+
+#[tokio::test]
+async fn events_should_be_sent_in_batches() {
+    let server = FakeHttp::new()
+        .post("/events", 200, r#"{"success":true}"#)
+        .start()
+        .await;
+
+    Sink::send(&juggler(server.url()), &events(5)).await.unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3);
+
+    let mut hosts: Vec<String> = Vec::new();
+    for request in &requests {
+        let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+        let events = body["events"].as_array().unwrap();
+        assert!(events.len() <= 2);
+        assert_eq!(body["source"], "test");
+        hosts.extend(events.iter().map(|e| e["host"].as_str().unwrap().to_string()));
+    }
+    hosts.sort();
+    assert_eq!(
+        hosts,
+        ["rmq0-test", "rmq1-test", "rmq2-test", "rmq3-test", "rmq4-test"]
+    );
+}
+
+#[tokio::test]
+async fn failed_batches_should_be_reported() {
+    let server = FakeHttp::new()
+        .post("/events", 503, "overloaded")
+        .start()
+        .await;
+    let juggler = juggler(server.url());
+
+    let results = juggler.send_batches(&events(3)).await;
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[1].batch, 2);
+    assert_eq!(results[1].events, 2..3);
+    assert!(results.iter().all(|r| r.result.is_err()));
+
+    let error = Sink::send(&juggler, &events(3)).await.unwrap_err().to_string();
+    assert!(error.starts_with("batch 1/2 (events 0..2): "));
+    assert!(error.contains("; batch 2/2 (events 2..3): "));
+    assert!(error.contains("503"));
+}
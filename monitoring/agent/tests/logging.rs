@@ -0,0 +1,76 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use agent::check::DynCheck;
+use agent::checks::zoo::ZooCheck;
+use agent::config::ZooConfig;
+use testkit::{transcripts, FakeZoo};
+use tracing_subscriber::EnvFilter;
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn collections_should_be_logged_as_json_per_host() {
+    let leader = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_LEADER)
+        .start()
+        .await;
+
+    let buffer = Buffer::default();
+    let writer = buffer.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::new("agent=info"))
+        .with_writer(move || writer.clone())
+        .json()
+        .flatten_event(true)
+        .with_current_span(true)
+        .with_span_list(false)
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let config: ZooConfig = toml::from_str(&format!(
+        r#"
+        cluster = "main"
+        hosts = ["{}", "127.0.0.1:1"]
+        "#,
+        leader.addr()
+    ))
+    .unwrap();
+    ZooCheck::new(&config).unwrap().run().await;
+
+    let raw = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    let lines: Vec<serde_json::Value> = raw
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+    let collected = lines.iter().find(|l| l["message"] == "collected").unwrap();
+    assert_eq!(collected["span"]["name"], "collect");
+    assert_eq!(collected["span"]["host"], leader.addr());
+    assert_eq!(collected["span"]["cluster"], "main");
+    assert_eq!(collected["span"]["check"], "zoo");
+    assert_eq!(collected["span"]["attempt"], 1);
+    for phase in ["latency_ms", "connect_ms", "read_ms", "parse_ms"] {
+        assert!(collected[phase].is_f64(), "{phase} is missing");
+    }
+
+    let failed = lines
+        .iter()
+        .find(|l| l["message"] == "failed to collect")
+        .unwrap();
+    assert_eq!(failed["level"], "WARN");
+    assert_eq!(failed["span"]["host"], "127.0.0.1:1");
+    assert!(failed["error"].as_str().unwrap().contains("refused"));
+    assert!(failed.get("read_ms").is_none());
+}
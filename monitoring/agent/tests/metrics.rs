@@ -0,0 +1,118 @@
+use reqwest::ClientBuilder;
+use tokio::io::AsyncReadExt;
+use tokio::net::{TcpListener, UdpSocket};
+
+use agent::config::MetricsConfig;
+use agent::juggler::{metrics, Event, Status};
+use agent::metrics::Metrics;
+use agent::sink::Sink;
+use testkit::FakeHttp;
+
+fn events() -> Vec<Event> {
+    vec![
+        Event {
+            host: String::from("zoo1-test"),
+            service: String::from("state"),
+            instance: String::from(""),
+            status: Status::OK,
+            description: String::from("leader. followers: 2/2"),
+            tags: vec![],
+            metrics: metrics(&[("zk_followers", 2.0)]),
+            cluster_level: false,
+        },
+        // events without metrics still get a status
+        Event {
+            host: String::from("zoo-test"),
+            service: String::from("leader-election"),
+            instance: String::from(""),
+            status: Status::OK,
+            description: String::from("leader: zoo1"),
+            tags: vec![],
+            metrics: Default::default(),
+            cluster_level: false,
+        },
+    ]
+}
+
+fn sink(protocol: &str, address: &str) -> Metrics {
+    let config: MetricsConfig = toml::from_str(&format!(
+        r#"
+        protocol = "{protocol}"
+        address = "{address}"
+        prefix = "zk"
+        extra_tags = {{ env = "test" }}
+        "#
+    ))
+    .unwrap();
+
+    Metrics::new(config, ClientBuilder::new().build().unwrap())
+}
+
+#[tokio::test]
+async fn graphite_should_receive_plaintext() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let sink = sink("graphite", &listener.local_addr().unwrap().to_string());
+
+    let received = tokio::spawn(async move {
+        let (mut conn, _) = listener.accept().await.unwrap();
+        let mut raw = String::new();
+        conn.read_to_string(&mut raw).await.unwrap();
+        raw
+    });
+    sink.send(&events()).await.unwrap();
+
+    let raw = received.await.unwrap();
+    let lines: Vec<&str> = raw
+        .lines()
+        .map(|line| line.rsplit_once(' ').unwrap().0)
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            "zk.zoo1-test.state.zk_followers 2",
+            "zk.zoo1-test.state.status 0",
+            "zk.zoo-test.leader-election.status 0",
+        ]
+    );
+    assert!(raw.ends_with('\n'));
+}
+
+#[tokio::test]
+async fn statsd_should_receive_gauges() {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let sink = sink("statsd", &socket.local_addr().unwrap().to_string());
+
+    sink.send(&events()).await.unwrap();
+
+    let mut buf = [0; 1500];
+    let n = socket.recv(&mut buf).await.unwrap();
+    assert_eq!(
+        std::str::from_utf8(&buf[..n]).unwrap(),
+        "zk.zoo1-test.state.zk_followers:2|g\n\
+         zk.zoo1-test.state.status:0|g\n\
+         zk.zoo-test.leader-election.status:0|g"
+    );
+}
+
+#[tokio::test]
+async fn influx_should_receive_line_protocol() {
+    let influx = FakeHttp::new().post("/write", 204, "").start().await;
+    let sink = sink("influx", &format!("{}/write", influx.url()));
+
+    sink.send(&events()).await.unwrap();
+
+    let requests = influx.requests();
+    assert_eq!(requests.len(), 1);
+    let points: Vec<&str> = requests[0]
+        .body
+        .lines()
+        .map(|line| line.rsplit_once(' ').unwrap().0)
+        .collect();
+    assert_eq!(
+        points,
+        [
+            "zk,env=test,host=zoo1-test,service=state zk_followers=2,status=0",
+            "zk,env=test,host=zoo-test,service=leader-election status=0",
+        ]
+    );
+}
@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use reqwest::ClientBuilder;
+
+use agent::checks;
+use agent::config::Config;
+use agent::preflight;
+use agent::state::State;
+use testkit::{transcripts, FakeHttp, FakeZoo};
+
+#[tokio::test]
+async fn every_target_should_be_tried_without_sending_events() {
+    let leader = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_LEADER)
+        .start()
+        .await;
+    let juggler = FakeHttp::new()
+        .post("/events", 200, r#"{"success":true}"#)
+        .start()
+        .await;
+
+    let config: Config = toml::from_str(&format!(
+        r#"
+        [juggler]
+        url = "{}"
+
+        [[zoo]]
+        cluster = "main"
+        hosts = ["{}", "127.0.0.1:1", "zoo.invalid"]
+        "#,
+        juggler.url(),
+        leader.addr(),
+    ))
+    .unwrap();
+    assert!(config.validate().is_empty());
+
+    let client = ClientBuilder::new().build().unwrap();
+    let mut outcomes = vec![preflight::check_juggler(client, &config.juggler).await];
+    let checks = checks::registry(&config, &Arc::new(State::memory())).unwrap();
+    outcomes.extend(preflight::check_targets(&checks).await);
+
+    let results: Vec<(&str, bool)> = outcomes
+        .iter()
+        .map(|o| (o.target.as_str(), o.result.is_ok()))
+        .collect();
+    assert_eq!(
+        results,
+        [
+            (juggler.url().as_str(), true),
+            (leader.addr().as_str(), true),
+            ("127.0.0.1:1", false),
+            ("zoo.invalid", false),
+        ]
+    );
+    assert!(outcomes[3]
+        .result
+        .as_ref()
+        .unwrap_err()
+        .starts_with("failed to resolve zoo.invalid"));
+
+    let requests = juggler.requests();
+    assert_eq!(requests.len(), 1);
+    let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+    assert!(body["events"].as_array().unwrap().is_empty());
+}
@@ -0,0 +1,137 @@
+use agent::check::DynCheck;
+use agent::checks::probe::{HttpProbe, TcpProbe};
+use agent::config::{HttpProbeConfig, TcpProbeConfig};
+use agent::juggler::Status;
+use testkit::{transcripts, FakeHttp, FakeZoo};
+
+#[tokio::test]
+async fn tcp_probe_should_match_banner() {
+    let server = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_FOLLOWER)
+        .start()
+        .await;
+
+    let config: TcpProbeConfig = toml::from_str(&format!(
+        r#"
+        name = "zk-port"
+        hosts = ["{}", "127.0.0.1:1"]
+        send = "mntr"
+        expect = "zk_server_state"
+        "#,
+        server.addr()
+    ))
+    .unwrap();
+
+    let report = TcpProbe::new(&config).run().await;
+
+    assert_eq!(report.events.len(), 2);
+    assert_eq!(report.events[0].service, "zk-port");
+    assert_eq!(report.events[0].status, Status::OK);
+    // both ports of 127.0.0.1 stay apart
+    assert_eq!(report.events[0].instance, server.addr().to_string());
+    assert_eq!(report.events[1].instance, "127.0.0.1:1");
+    assert_eq!(report.events[1].status, Status::CRIT);
+    assert!(report.events[1].description.starts_with("failed in"));
+}
+
+#[tokio::test]
+async fn tcp_probe_should_fail_on_unexpected_banner() {
+    let server = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_FOLLOWER)
+        .start()
+        .await;
+
+    let config: TcpProbeConfig = toml::from_str(&format!(
+        r#"
+        name = "zk-port"
+        hosts = ["{}"]
+        send = "mntr"
+        expect = "leader"
+        "#,
+        server.addr()
+    ))
+    .unwrap();
+
+    let report = TcpProbe::new(&config).run().await;
+
+    assert_eq!(report.events[0].status, Status::CRIT);
+    assert!(report.events[0]
+        .description
+        .ends_with("connection closed before \"leader\" was received"));
+}
+
+#[tokio::test]
+async fn http_probe_should_assert_status_body_and_json() {
+    let server = FakeHttp::new()
+        .get("/health", 200, r#"{"status":"ok","checks":[{"name":"db","up":true}]}"#)
+        .get("/broken", 200, r#"{"status":"degraded","checks":[]}"#)
+        .get("/missing", 404, r#"{}"#)
+        .start()
+        .await;
+
+    let config: HttpProbeConfig = toml::from_str(&format!(
+        r#"
+        name = "api-health"
+        urls = ["{0}/health", "{0}/broken", "{0}/missing"]
+        status = 200
+        body = '"status":\s*"\w+"'
+        json_path = "$.status"
+        json_equals = "ok"
+        max_latency_ms = 1000
+        "#,
+        server.url()
+    ))
+    .unwrap();
+
+    let report = HttpProbe::new(&config).unwrap().run().await;
+    let url = server.url();
+
+    assert_eq!(report.events.len(), 3);
+    assert_eq!(report.events[0].host, "127.0.0.1");
+    assert_eq!(report.events[0].instance, format!("{url}/health"));
+    assert_eq!(report.events[0].status, Status::OK);
+    assert_eq!(report.events[1].host, "127.0.0.1");
+    assert_eq!(report.events[1].instance, format!("{url}/broken"));
+    assert_eq!(report.events[1].status, Status::CRIT);
+    assert!(report.events[1]
+        .description
+        .ends_with("$.status is \"degraded\", expected \"ok\""));
+    assert!(report.events[2].description.ends_with("status 404, expected 200"));
+}
+
+#[tokio::test]
+async fn http_probe_should_only_read_the_head_of_the_body() {
+    let body = format!("{}marker", "x".repeat(1024 * 1024));
+    let server = FakeHttp::new().get("/large", 200, &body).start().await;
+
+    let config: HttpProbeConfig = toml::from_str(&format!(
+        r#"
+        name = "api-health"
+        urls = ["{}/large"]
+        body = "marker"
+        "#,
+        server.url()
+    ))
+    .unwrap();
+
+    let report = HttpProbe::new(&config).unwrap().run().await;
+
+    assert_eq!(report.events[0].status, Status::CRIT);
+    assert!(report.events[0]
+        .description
+        .ends_with("body does not match \"marker\""));
+}
+
+#[test]
+fn json_equals_without_path_should_be_rejected() {
+    let config: HttpProbeConfig = toml::from_str(
+        r#"
+        name = "api-health"
+        urls = ["http://api.example.com/health"]
+        json_equals = true
+        "#,
+    )
+    .unwrap();
+
+    assert!(HttpProbe::new(&config).is_err());
+}
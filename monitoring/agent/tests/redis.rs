@@ -0,0 +1,129 @@
+use agent::check::DynCheck;
+use agent::checks::redis::RedisCheck;
+use agent::config::RedisConfig;
+use agent::juggler::Status;
+use agent::secret::Credentials;
+use testkit::redis::{array, bulk, integer};
+use testkit::FakeRedis;
+
+fn config(hosts: Vec<String>) -> RedisConfig {
+    RedisConfig {
+        cluster: String::from("cache"),
+        hosts,
+        discovery: None,
+        expected_replicas: None,
+        max_lag_bytes: 1024,
+        max_last_io_seconds: 10,
+        memory_warn: 0.8,
+        memory_crit: 0.95,
+        credentials: Credentials::default(),
+        naming: None,
+        enrichment: None,
+    }
+}
+
+#[tokio::test]
+async fn replicated_pair_should_be_evaluated() {
+    let master = FakeRedis::new()
+        .reply(
+            "INFO",
+            &bulk("# Replication\r\nrole:master\r\nconnected_slaves:1\r\n# Memory\r\nused_memory:850\r\nmaxmemory:1000\r\n# Stats\r\nrejected_connections:0\r\n"),
+        )
+        .reply(
+            "ROLE",
+            &array(&[
+                bulk("master"),
+                integer(5000),
+                array(&[array(&[bulk("10.0.0.2"), bulk("6379"), bulk("4990")])]),
+            ]),
+        )
+        .start()
+        .await;
+    let replica = FakeRedis::new()
+        .reply(
+            "INFO",
+            &bulk("# Replication\r\nrole:slave\r\nmaster_link_status:down\r\nmaster_last_io_seconds_ago:-1\r\n"),
+        )
+        .reply(
+            "ROLE",
+            &array(&[
+                bulk("slave"),
+                bulk("10.0.0.1"),
+                integer(6379),
+                bulk("connect"),
+                integer(-1),
+            ]),
+        )
+        .start()
+        .await;
+
+    let check = RedisCheck::new(&config(vec![master.addr(), replica.addr()])).unwrap();
+    let report = check.run().await;
+
+    assert!(report.targets.iter().all(|t| t.error.is_none()));
+    assert_eq!(report.events.len(), 6);
+
+    let replication = &report.events[0];
+    assert_eq!(replication.service, "redis-replication");
+    assert_eq!(replication.status, Status::OK);
+    assert_eq!(replication.description, "master. replicas: 1/1, max lag: 10 bytes");
+
+    let memory = &report.events[1];
+    assert_eq!(memory.status, Status::WARN);
+    assert_eq!(memory.description, "used memory: 850/1000 bytes (85%)");
+
+    let replica = &report.events[3];
+    assert_eq!(replica.status, Status::CRIT);
+    assert_eq!(replica.description, "replica of 10.0.0.1:6379. link: down, state: connect");
+}
+
+#[tokio::test]
+async fn server_errors_should_fail_collect() {
+    let server = FakeRedis::new()
+        .reply("INFO", "-NOAUTH Authentication required.\r\n")
+        .start()
+        .await;
+
+    let check = RedisCheck::new(&config(vec![server.addr()])).unwrap();
+    let report = check.run().await;
+
+    assert!(report.events.is_empty());
+    assert_eq!(
+        report.targets[0].error.as_deref(),
+        Some("INFO: NOAUTH Authentication required.")
+    );
+}
+
+#[tokio::test]
+async fn password_should_be_sent_first() {
+    let server = FakeRedis::new()
+        .password("hunter2")
+        .reply("INFO", &bulk("role:master\r\n"))
+        .reply("ROLE", &array(&[bulk("master"), integer(0), array(&[])]))
+        .start()
+        .await;
+    let check = |password: &str| {
+        let mut config = config(vec![server.addr()]);
+        config.credentials = toml::from_str(&format!(
+            r#"
+            login = "monitoring"
+            password = "{password}"
+            "#
+        ))
+        .unwrap();
+        RedisCheck::new(&config).unwrap()
+    };
+
+    let report = check("hunter2").run().await;
+    assert!(report.targets[0].error.is_none());
+    assert_eq!(
+        report.events[0].description,
+        "master. replicas: 0/0, max lag: 0 bytes"
+    );
+
+    let report = check("wrong").run().await;
+    assert_eq!(
+        report.targets[0].error.as_deref(),
+        Some("AUTH: WRONGPASS invalid username-password pair")
+    );
+}
@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use reqwest::ClientBuilder;
+use tokio::net::TcpListener;
+
+use agent::agent::Agent;
+use agent::checks;
+use agent::config::Config;
+use agent::juggler::Juggler;
+use agent::sink::Sink;
+use agent::state::State;
+use agent::status::{self, StatusBoard};
+use testkit::{transcripts, FakeHttp, FakeZoo};
+
+#[tokio::test]
+async fn status_api_should_show_latest_results() {
+    let leader = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_LEADER)
+        .start()
+        .await;
+    let juggler = FakeHttp::new()
+        .post("/events", 200, r#"{"success":true}"#)
+        .start()
+        .await;
+
+    let config: Config = toml::from_str(&format!(
+        r#"
+        [juggler]
+        url = "{}"
+
+        [[zoo]]
+        cluster = "main"
+        hosts = ["{}", "127.0.0.1:1"]
+
+        [zoo.naming]
+        strategy = "alias"
+        aliases = {{ "127.0.0.1:1" = "dead" }}
+        "#,
+        juggler.url(),
+        leader.addr(),
+    ))
+    .unwrap();
+
+    let client = ClientBuilder::new().build().unwrap();
+    let sinks: Vec<Box<dyn Sink>> = vec![Box::new(Juggler::new(
+        client.clone(),
+        config.juggler.url.clone(),
+        config.juggler.source.clone(),
+    ))];
+
+    let board = Arc::new(StatusBoard::default());
+    let checks = checks::registry(&config, &Arc::new(State::memory())).unwrap();
+    let agent = Agent::new(checks, sinks).with_status(board.clone());
+    agent.run_once().await.unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(status::serve(listener, board));
+
+    let rows: serde_json::Value = client
+        .get(format!("{url}/api/status"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let rows = rows.as_array().unwrap();
+
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0]["host"], "127.0.0.1-test");
+    assert_eq!(rows[0]["service"], "state");
+    assert!(rows[0]["status"].is_string());
+    assert!(rows[0]["last_success"].is_u64());
+    assert!(rows[0]["last_error"].is_null());
+
+    // never collected, its state comes from the error
+    assert_eq!(rows[1]["host"], "dead");
+    assert_eq!(rows[1]["service"], "state");
+    assert_eq!(rows[1]["status"], "CRIT");
+    assert!(rows[1]["last_success"].is_null());
+    assert!(rows[1]["last_error"].is_string());
+
+    assert_eq!(rows[2]["host"], "main");
+    assert_eq!(rows[2]["service"], "leader-election");
+
+    let page = client.get(&url).send().await.unwrap().text().await.unwrap();
+    assert!(page.contains("127.0.0.1-test"));
+    assert!(page.contains("zoo/main"));
+}
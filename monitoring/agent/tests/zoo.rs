@@ -0,0 +1,72 @@
+use agent::check::DynCheck;
+use agent::checks::zoo::ZooCheck;
+use agent::config::ZooConfig;
+use agent::juggler::Status;
+use testkit::{transcripts, FakeZoo, Mode};
+
+fn config(hosts: &[String], retries: u32) -> ZooConfig {
+    toml::from_str(&format!(
+        r#"
+        cluster = "main"
+        hosts = {hosts:?}
+        retries = {retries}
+        "#
+    ))
+    .unwrap()
+}
+
+#[tokio::test]
+async fn timed_out_members_should_be_retried() {
+    let leader = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_LEADER)
+        .mode(Mode::Flaky(1))
+        .start()
+        .await;
+    let follower = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_FOLLOWER)
+        .start()
+        .await;
+    let hosts = [leader.addr(), follower.addr()];
+
+    let report = ZooCheck::new(&config(&hosts, 1)).unwrap().run().await;
+
+    assert!(report.targets.iter().all(|t| t.error.is_none()));
+    assert_eq!(report.events[0].description, "leader. followers: 2/1");
+}
+
+#[tokio::test]
+async fn failed_members_should_get_a_status_from_their_error() {
+    let silent = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_LEADER)
+        .mode(Mode::Silent)
+        .start()
+        .await;
+    let follower = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_FOLLOWER)
+        .start()
+        .await;
+    let hosts = [silent.addr(), follower.addr(), String::from("127.0.0.1:1")];
+
+    let report = ZooCheck::new(&config(&hosts, 0)).unwrap().run().await;
+
+    let state = |host: &str| {
+        report
+            .events
+            .iter()
+            .find(|e| e.service == "state" && e.host == host)
+            .unwrap()
+    };
+
+    let silent_state = state(&hosts[0]);
+    assert_eq!(silent_state.status, Status::WARN);
+    assert_eq!(
+        silent_state.description,
+        format!("timed out reading data from {}", hosts[0])
+    );
+
+    let refused = state("127.0.0.1:1");
+    assert_eq!(refused.status, Status::CRIT);
+    assert!(refused
+        .description
+        .starts_with("failed to connect to 127.0.0.1:1: "));
+}
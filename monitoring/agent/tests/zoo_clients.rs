@@ -0,0 +1,91 @@
+use agent::check::{DynCheck, Report};
+use agent::checks::zoo_clients::ZooClientsCheck;
+use agent::config::ZooConfig;
+use agent::juggler::{Event, Status};
+use testkit::{transcripts, FakeZoo, RunningZoo};
+
+fn check(hosts: &[String]) -> ZooClientsCheck {
+    let config: ZooConfig = toml::from_str(&format!(
+        r#"
+        cluster = "main"
+        hosts = {hosts:?}
+
+        [clients]
+        max_client_connections = 4
+        max_member_connections = 10
+        top = 2
+        "#
+    ))
+    .unwrap();
+
+    ZooClientsCheck::new(&config, config.clients.as_ref().unwrap()).unwrap()
+}
+
+fn clients<'a>(report: &'a Report, host: &str) -> &'a Event {
+    report
+        .events
+        .iter()
+        .find(|e| e.service == "clients" && e.host == host)
+        .unwrap()
+}
+
+async fn member() -> RunningZoo {
+    FakeZoo::new()
+        .transcript("cons", transcripts::CONS)
+        .transcript("wchs", transcripts::WCHS)
+        .transcript("wchc", transcripts::WCHC)
+        .start()
+        .await
+}
+
+#[tokio::test]
+async fn noisy_client_should_be_named() {
+    let (zoo1, zoo2) = (member().await, member().await);
+    let hosts = [zoo1.addr(), zoo2.addr()];
+
+    let report = check(&hosts).run().await;
+
+    assert!(report.targets.iter().all(|t| t.error.is_none()));
+
+    let member = clients(&report, &hosts[0]);
+    assert_eq!(member.status, Status::OK);
+    assert_eq!(
+        member.description,
+        "connections: 5/10, watches: 7/100000\n\
+         10.0.0.9: 3 connections, 5 watches\n\
+         10.0.0.5: 1 connections, 1 watches"
+    );
+
+    let ensemble = clients(&report, "main");
+    assert_eq!(ensemble.status, Status::WARN);
+    assert_eq!(
+        ensemble.description,
+        "clients: 3, over the limits: 1, limits: 4 connections, 10000 watches\n\
+         10.0.0.9: 6 connections, 10 watches\n\
+         10.0.0.5: 2 connections, 2 watches"
+    );
+    assert_eq!(ensemble.metrics["busiest_client_connections"], 6.0);
+}
+
+#[tokio::test]
+async fn member_refusing_the_commands_should_warn() {
+    let zoo1 = member().await;
+    let zoo2 = FakeZoo::new()
+        .transcript("cons", transcripts::CONS)
+        .transcript(
+            "wchs",
+            "wchs is not executed because it is not in the whitelist.\n",
+        )
+        .start()
+        .await;
+    let hosts = [zoo1.addr(), zoo2.addr()];
+
+    let report = check(&hosts).run().await;
+
+    let refused = clients(&report, &hosts[1]);
+    assert_eq!(refused.status, Status::WARN);
+    assert!(refused.description.contains("Total watches is missing"));
+    assert!(clients(&report, "main")
+        .description
+        .starts_with("clients: 3,"));
+}
@@ -0,0 +1,3 @@
+pub mod juggler;
+pub mod rmq;
+pub mod secret;
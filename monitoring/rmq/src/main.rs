@@ -1,44 +1,96 @@
-mod juggler;
-mod rmq;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
 
-use reqwest::{header::CONTENT_TYPE, ClientBuilder};
 use serde::Deserialize;
 
-use juggler::{jugglerify, Event, Payload, Status};
-use rmq::{federify, Info, UpstreamStatus};
+use rmq::juggler::{send, Event, Payload};
+use rmq::rmq::{
+    build_client, compute, federify, fetch, shovel_events, shovelify, Api, Info, Shovel, Tls,
+};
+use rmq::secret::{self, Credentials, Secret};
 
 const TIMEOUT: u64 = 3;
 
 #[derive(Deserialize, Debug)]
 struct Config {
     rmq_hosts: String,
-    rmq_login: String,
-    rmq_password: String,
+    rmq_login: Option<String>,
+    rmq_login_file: Option<PathBuf>,
+    rmq_password: Option<Secret>,
+    rmq_password_file: Option<PathBuf>,
+    // TOML file with a [credentials] section
+    rmq_config: Option<PathBuf>,
+    #[serde(default = "default_scheme")]
+    rmq_scheme: String,
+    #[serde(default = "default_port")]
+    rmq_port: u16,
+    #[serde(default)]
+    rmq_path_prefix: String,
+    // PEM bundle used instead of the system roots
+    rmq_ca_bundle: Option<PathBuf>,
+    // PEM certificate and PKCS#8 PEM key presented to the brokers
+    rmq_client_cert: Option<PathBuf>,
+    rmq_client_key: Option<PathBuf>,
+    // labs only: accept any broker certificate
+    #[serde(default)]
+    rmq_insecure: bool,
     juggler_url: String,
 }
 
-fn compute(info: Vec<Info>) -> Event {
-    let status = if info.iter().all(|i| i.status == UpstreamStatus::Running) {
-        Status::OK
-    } else {
-        Status::WARN
-    };
+#[derive(Deserialize, Debug, Default)]
+struct FileConfig {
+    #[serde(default)]
+    credentials: Credentials,
+}
+
+fn default_scheme() -> String {
+    String::from("http")
+}
 
-    let node = info[0].node.split("@").last().unwrap();
+fn default_port() -> u16 {
+    15672
+}
 
-    let mut description = format!("Federation {} upstreams status:\n", node);
-    for i in &info {
-        let upstream_name = i.upstream.split(".").collect::<Vec<&str>>()[0];
-        description = format!("{}\n{}: {}", description, upstream_name, i.status);
+impl Config {
+    fn tls(&self) -> Tls {
+        Tls {
+            ca_bundle: self.rmq_ca_bundle.clone(),
+            client_cert: self.rmq_client_cert.clone(),
+            client_key: self.rmq_client_key.clone(),
+            insecure: self.rmq_insecure,
+        }
     }
 
-    Event {
-        host: format!("{}-test", node),
-        service: String::from("federation"),
-        instance: String::from(""),
-        status,
-        description,
-        tags: vec![],
+    fn api(&self) -> Api {
+        Api {
+            scheme: self.rmq_scheme.clone(),
+            port: self.rmq_port,
+            path_prefix: self.rmq_path_prefix.clone(),
+        }
+    }
+
+    /// Env vars win over the config file, values win over files.
+    fn credentials(&self) -> Result<(String, Secret), Box<dyn Error>> {
+        let env = Credentials {
+            login: self.rmq_login.clone(),
+            login_file: self.rmq_login_file.clone(),
+            password: self.rmq_password.clone(),
+            password_file: self.rmq_password_file.clone(),
+        };
+
+        let file = match &self.rmq_config {
+            Some(path) => {
+                let raw = fs::read_to_string(path)
+                    .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+                toml::from_str::<FileConfig>(&raw)
+                    .map_err(|e| format!("failed to parse {}: {}", path.display(), e.message()))?
+            }
+            None => FileConfig::default(),
+        };
+
+        Ok(secret::resolve(&[&env, &file.credentials])?)
     }
 }
 
@@ -46,46 +98,37 @@ fn compute(info: Vec<Info>) -> Event {
 async fn main() {
     env_logger::init();
 
-    let config = envy::from_env::<Config>()
-        .expect("RMQ_HOSTS and RMQ_LOGIN and RMQ_PASSWORD and JUGGLER_URL env vars must be set");
+    let config =
+        envy::from_env::<Config>().expect("RMQ_HOSTS and JUGGLER_URL env vars must be set");
 
-    let client = ClientBuilder::new()
-        .timeout(tokio::time::Duration::from_secs(TIMEOUT))
-        .build()
-        .unwrap();
+    let (login, password) = match config.credentials() {
+        Err(e) => {
+            log::error!("failed to load RMQ credentials: {}", e);
+            return;
+        }
+        Ok(credentials) => credentials,
+    };
+    let credentials = (login.as_str(), &password);
+    let api = config.api();
 
-    let hosts = config.rmq_hosts.split(",");
+    if config.rmq_insecure {
+        log::warn!("RMQ certificate verification is disabled");
+    }
+    let client = match build_client(&config.tls(), Duration::from_secs(TIMEOUT)) {
+        Err(e) => {
+            log::error!("failed to set up RMQ client: {}", e);
+            return;
+        }
+        Ok(client) => client,
+    };
 
-    let bodies = futures::future::join_all(hosts.into_iter().map(|host| {
-        let url: String = federify(host);
+    let hosts: Vec<&str> = config.rmq_hosts.split(",").collect();
 
-        let client = &client;
-        let login = &config.rmq_login;
-        let password = &config.rmq_password;
+    let federation: Vec<Vec<Info>> = fetch(&client, &api, credentials, &hosts, federify).await;
+    let mut events: Vec<Event> = federation.iter().filter_map(|info| compute(info)).collect();
 
-        async move {
-            client
-                .get(url)
-                .basic_auth(login, Some(password))
-                .send()
-                .await?
-                .json()
-                .await
-        }
-    }))
-    .await;
-
-    let events = bodies
-        .into_iter()
-        .filter(|b| match b {
-            Err(e) => {
-                log::warn!("failed to reach RMQ: {}", e);
-                false
-            }
-            _ => true,
-        })
-        .map(|b| compute(b.unwrap()))
-        .collect();
+    let shovels: Vec<Vec<Shovel>> = fetch(&client, &api, credentials, &hosts, shovelify).await;
+    events.extend(shovel_events(shovels));
 
     // println!("{:?}", events);
 
@@ -94,25 +137,8 @@ async fn main() {
         events,
     };
 
-    let res = client
-        .post(jugglerify(config.juggler_url))
-        .header(CONTENT_TYPE, "application/json")
-        .json(&payload)
-        .send()
-        .await;
-
-    let response = match res {
-        Err(e) => {
-            log::error!("failed to reach Juggler: {:?}", e);
-            return;
-        }
-        Ok(response) => response,
-    };
-
-    let reply: Result<serde_json::Value, reqwest::Error> = response.json().await;
-
-    match reply {
+    match send(&client, config.juggler_url, &payload).await {
         Ok(o) => log::info!("rmq monitoring completed successfully. Reply: {:#?}", o),
-        Err(e) => log::error!("failed to parse Juggler response: {:?}", e),
+        Err(e) => log::error!("failed to send events to Juggler: {:?}", e),
     }
 }
@@ -1,6 +1,15 @@
+use std::collections::HashSet;
+use std::error::Error;
 use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
 
-use serde::Deserialize;
+use reqwest::{Certificate, Client, ClientBuilder, Identity};
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::juggler::{Event, Status};
+use crate::secret::Secret;
 
 #[derive(Deserialize, Debug, PartialEq)]
 #[serde(rename_all(deserialize = "lowercase"))]
@@ -27,6 +36,294 @@ pub struct Info {
     pub status: UpstreamStatus,
 }
 
-pub fn federify(host: &str) -> String {
-    format!("http://{}:15672/api/federation-links", host)
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename_all(deserialize = "lowercase"))]
+pub enum ShovelState {
+    Running,
+    Starting,
+    Terminated,
+    // blocked, flow and whatever newer brokers report
+    #[serde(other)]
+    Unknown,
+}
+
+impl fmt::Display for ShovelState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShovelState::Running => write!(f, "running"),
+            ShovelState::Starting => write!(f, "starting"),
+            ShovelState::Terminated => write!(f, "terminated"),
+            ShovelState::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Shovel {
+    pub node: String,
+    pub name: String,
+    // static shovels are not bound to a vhost
+    #[serde(default)]
+    pub vhost: String,
+    pub state: ShovelState,
+    // last error reported by a terminated shovel
+    pub reason: Option<String>,
+}
+
+/// Where the management API lives on every node of a cluster.
+#[derive(Debug)]
+pub struct Api {
+    pub scheme: String,
+    pub port: u16,
+    pub path_prefix: String,
+}
+
+impl Api {
+    pub fn url(&self, host: &str, endpoint: &str) -> String {
+        let prefix = self.path_prefix.trim_matches('/');
+        if prefix.is_empty() {
+            format!("{}://{}:{}/api/{}", self.scheme, host, self.port, endpoint)
+        } else {
+            format!(
+                "{}://{}:{}/{}/api/{}",
+                self.scheme, host, self.port, prefix, endpoint
+            )
+        }
+    }
+}
+
+/// TLS settings of the management API, the same on every node of a cluster.
+#[derive(Deserialize, Debug, Default)]
+pub struct Tls {
+    // PEM bundle used instead of the system roots
+    pub ca_bundle: Option<PathBuf>,
+    // PEM certificate and PKCS#8 PEM key presented to the brokers
+    pub client_cert: Option<PathBuf>,
+    pub client_key: Option<PathBuf>,
+    // labs only: accept any broker certificate
+    #[serde(default)]
+    pub insecure: bool,
+}
+
+pub fn build_client(tls: &Tls, timeout: Duration) -> Result<Client, Box<dyn Error + Send + Sync>> {
+    let mut builder = ClientBuilder::new().timeout(timeout);
+
+    if let Some(path) = &tls.ca_bundle {
+        let pem = fs::read(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        for cert in Certificate::from_pem_bundle(&pem)? {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    match (&tls.client_cert, &tls.client_key) {
+        (Some(cert), Some(key)) => {
+            let cert =
+                fs::read(cert).map_err(|e| format!("failed to read {}: {}", cert.display(), e))?;
+            let key =
+                fs::read(key).map_err(|e| format!("failed to read {}: {}", key.display(), e))?;
+            builder = builder.identity(Identity::from_pkcs8_pem(&cert, &key)?);
+        }
+        (None, None) => (),
+        _ => return Err("client certificate and key must be set together".into()),
+    }
+
+    if tls.insecure {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+
+    Ok(builder.build()?)
+}
+
+pub fn federify(api: &Api, host: &str) -> String {
+    api.url(host, "federation-links")
+}
+
+pub fn shovelify(api: &Api, host: &str) -> String {
+    api.url(host, "shovels")
+}
+
+/// None for a broker without federation links, e.g. one running only shovels.
+pub fn compute(info: &[Info]) -> Option<Event> {
+    let node = info.first()?.node.split("@").last().unwrap();
+
+    let status = if info.iter().all(|i| i.status == UpstreamStatus::Running) {
+        Status::OK
+    } else {
+        Status::WARN
+    };
+
+    let mut description = format!("Federation {} upstreams status:\n", node);
+    for i in info {
+        let upstream_name = i.upstream.split(".").collect::<Vec<&str>>()[0];
+        description = format!("{}\n{}: {}", description, upstream_name, i.status);
+    }
+
+    Some(Event {
+        host: format!("{}-test", node),
+        service: String::from("federation"),
+        instance: String::from(""),
+        status,
+        description,
+        tags: vec![],
+    })
+}
+
+pub fn compute_shovel(shovel: &Shovel) -> Event {
+    let status = match shovel.state {
+        ShovelState::Running => Status::OK,
+        ShovelState::Starting | ShovelState::Unknown => Status::WARN,
+        ShovelState::Terminated => Status::CRIT,
+    };
+
+    let node = shovel.node.split("@").last().unwrap();
+
+    let mut description = format!("Shovel {} on {}: {}", shovel.name, node, shovel.state);
+    if let Some(reason) = &shovel.reason {
+        description = format!("{}\nlast error: {}", description, reason);
+    }
+
+    Event {
+        host: format!("{}-test", node),
+        service: String::from("shovel"),
+        instance: shovel.name.clone(),
+        status,
+        description,
+        tags: vec![],
+    }
+}
+
+pub async fn fetch<T: DeserializeOwned>(
+    client: &Client,
+    api: &Api,
+    (login, password): (&str, &Secret),
+    hosts: &[&str],
+    urlify: fn(&Api, &str) -> String,
+) -> Vec<T> {
+    let bodies = futures::future::join_all(hosts.iter().map(|host| {
+        let url: String = urlify(api, host);
+
+        async move {
+            let body = async {
+                client
+                    .get(&url)
+                    .basic_auth(login, Some(password.expose()))
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .bytes()
+                    .await
+            }
+            .await
+            .map_err(|e| format!("failed to reach RMQ: {}", e))?;
+
+            // a reply the monitor does not understand is not a network problem
+            serde_json::from_slice(&body)
+                .map_err(|e| format!("failed to parse RMQ reply from {}: {}", url, e))
+        }
+    }))
+    .await;
+
+    bodies
+        .into_iter()
+        .filter_map(|b| match b {
+            Err(e) => {
+                log::warn!("{}", e);
+                None
+            }
+            Ok(body) => Some(body),
+        })
+        .collect()
+}
+
+/// Every node reports shovels of the whole cluster, so each one is kept once.
+pub fn shovel_events(shovels: Vec<Vec<Shovel>>) -> Vec<Event> {
+    let mut seen = HashSet::new();
+
+    shovels
+        .into_iter()
+        .flatten()
+        .filter(|s| seen.insert((s.node.clone(), s.vhost.clone(), s.name.clone())))
+        .map(|s| compute_shovel(&s))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls_should_respect_api_settings() {
+        let api = Api {
+            scheme: String::from("http"),
+            port: 15672,
+            path_prefix: String::from(""),
+        };
+        assert_eq!(
+            federify(&api, "rmq1.example.com"),
+            "http://rmq1.example.com:15672/api/federation-links"
+        );
+
+        let api = Api {
+            scheme: String::from("https"),
+            port: 443,
+            path_prefix: String::from("/rabbitmq/"),
+        };
+        assert_eq!(
+            shovelify(&api, "rmq1.example.com"),
+            "https://rmq1.example.com:443/rabbitmq/api/shovels"
+        );
+    }
+
+    #[test]
+    fn shovels_should_parse() {
+        let body = r#"[
+            {
+                "node": "rabbit@rmq1.example.com",
+                "name": "orders",
+                "vhost": "/",
+                "type": "dynamic",
+                "state": "running",
+                "src_uri": "amqp://",
+                "timestamp": "2023-04-20 10:00:00"
+            },
+            {
+                "node": "rabbit@rmq2.example.com",
+                "name": "billing",
+                "type": "static",
+                "state": "terminated",
+                "reason": "{badmatch,{error,econnrefused}}",
+                "timestamp": "2023-04-20 10:00:00"
+            }
+        ]"#;
+
+        let shovels: Vec<Shovel> = serde_json::from_str(body).unwrap();
+
+        assert_eq!(shovels.len(), 2);
+        assert_eq!(shovels[0].state, ShovelState::Running);
+        assert_eq!(shovels[0].vhost, "/");
+        assert_eq!(shovels[0].reason, None);
+        assert_eq!(shovels[1].state, ShovelState::Terminated);
+        assert_eq!(shovels[1].vhost, "");
+        assert_eq!(
+            shovels[1].reason.as_deref(),
+            Some("{badmatch,{error,econnrefused}}")
+        );
+    }
+
+    #[test]
+    fn unknown_shovel_states_should_warn() {
+        let body = r#"[
+            {"node": "rabbit@rmq1.example.com", "name": "orders", "state": "blocked"}
+        ]"#;
+
+        let shovels: Vec<Shovel> = serde_json::from_str(body).unwrap();
+
+        assert_eq!(shovels[0].state, ShovelState::Unknown);
+        let event = compute_shovel(&shovels[0]);
+        assert!(matches!(event.status, Status::WARN));
+        assert_eq!(
+            event.description,
+            "Shovel orders on rmq1.example.com: unknown"
+        );
+    }
 }
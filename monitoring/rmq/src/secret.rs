@@ -0,0 +1,146 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+/// A value that never shows up in logs: `Debug` and `Display` are redacted.
+#[derive(Deserialize, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Secret([redacted])")
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[redacted]")
+    }
+}
+
+/// Reads a secret mounted as a file, Kubernetes-secret style.
+/// A single trailing newline is stripped.
+pub fn read_secret_file(path: &Path) -> Result<Secret, String> {
+    let mut value = fs::read_to_string(path)
+        .map_err(|e| format!("failed to read secret from {}: {}", path.display(), e))?;
+
+    if value.ends_with('\n') {
+        value.pop();
+        if value.ends_with('\r') {
+            value.pop();
+        }
+    }
+
+    Ok(Secret(value))
+}
+
+/// One source of credentials: either values or files holding them.
+/// A value wins over a file within the same source.
+#[derive(Deserialize, Debug, Default)]
+pub struct Credentials {
+    pub login: Option<String>,
+    pub login_file: Option<PathBuf>,
+    pub password: Option<Secret>,
+    pub password_file: Option<PathBuf>,
+}
+
+impl Credentials {
+    pub fn login(&self) -> Result<Option<String>, String> {
+        match (&self.login, &self.login_file) {
+            (Some(login), _) => Ok(Some(login.clone())),
+            (None, Some(path)) => Ok(Some(read_secret_file(path)?.0)),
+            (None, None) => Ok(None),
+        }
+    }
+
+    pub fn password(&self) -> Result<Option<Secret>, String> {
+        match (&self.password, &self.password_file) {
+            (Some(password), _) => Ok(Some(password.clone())),
+            (None, Some(path)) => Ok(Some(read_secret_file(path)?)),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+/// Picks login and password from the first source that has them.
+pub fn resolve(sources: &[&Credentials]) -> Result<(String, Secret), String> {
+    let mut login = None;
+    let mut password = None;
+
+    for source in sources {
+        if login.is_none() {
+            login = source.login()?;
+        }
+        if password.is_none() {
+            password = source.password()?;
+        }
+    }
+
+    match (login, password) {
+        (Some(login), Some(password)) => Ok((login, password)),
+        (None, _) => Err(String::from("login is not set")),
+        (_, None) => Err(String::from("password is not set")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secret_should_be_redacted() {
+        let secret = Secret(String::from("hunter2"));
+
+        assert_eq!(format!("{}", secret), "[redacted]");
+        assert_eq!(format!("{:?}", secret), "Secret([redacted])");
+        assert_eq!(
+            format!("{:?}", Some(secret.clone())),
+            "Some(Secret([redacted]))"
+        );
+        assert_eq!(secret.expose(), "hunter2");
+    }
+
+    #[test]
+    fn resolve_should_prefer_earlier_sources() {
+        let path = std::env::temp_dir().join(format!("rmq-password-{}", std::process::id()));
+        fs::write(&path, "from-file\n").unwrap();
+
+        let env = Credentials {
+            password_file: Some(path.clone()),
+            ..Default::default()
+        };
+        let file = Credentials {
+            login: Some(String::from("monitoring")),
+            password: Some(Secret(String::from("from-config"))),
+            ..Default::default()
+        };
+
+        let (login, password) = resolve(&[&env, &file]).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(login, "monitoring");
+        assert_eq!(password.expose(), "from-file");
+    }
+
+    #[test]
+    fn resolve_should_fail_without_password() {
+        let env = Credentials {
+            login: Some(String::from("monitoring")),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            resolve(&[&env]).err(),
+            Some(String::from("password is not set"))
+        );
+    }
+}
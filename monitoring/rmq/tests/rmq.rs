@@ -0,0 +1,155 @@
+use reqwest::ClientBuilder;
+use serde::Deserialize;
+
+use rmq::juggler::{send, Event, Payload};
+use rmq::rmq::{compute, federify, fetch, shovel_events, shovelify, Api, Info, Shovel};
+use rmq::secret::Secret;
+use testkit::{FakeHttp, RunningHttp};
+
+const FEDERATION_LINKS: &str = r#"[
+    {"node": "rabbit@rmq1.example.com", "upstream": "orders.dc1", "status": "running"},
+    {"node": "rabbit@rmq1.example.com", "upstream": "billing.dc1", "status": "starting"}
+]"#;
+
+const SHOVELS: &str = r#"[
+    {"node": "rabbit@rmq1.example.com", "name": "orders", "vhost": "/", "type": "dynamic", "state": "running"},
+    {"node": "rabbit@rmq2.example.com", "name": "billing", "vhost": "/", "type": "dynamic", "state": "terminated", "reason": "econnrefused"}
+]"#;
+
+fn password() -> Secret {
+    serde_json::from_str(r#""guest""#).unwrap()
+}
+
+fn api(server: &RunningHttp, path_prefix: &str) -> Api {
+    Api {
+        scheme: String::from("http"),
+        port: server.port(),
+        path_prefix: path_prefix.to_string(),
+    }
+}
+
+#[derive(Deserialize)]
+struct Reply {
+    success: bool,
+}
+
+#[tokio::test]
+async fn federation_and_shovels_should_be_sent_to_juggler() {
+    let broker = FakeHttp::new()
+        .basic_auth("guest", "guest")
+        .get("/api/federation-links", 200, FEDERATION_LINKS)
+        .get("/api/shovels", 200, SHOVELS)
+        .start()
+        .await;
+    let juggler = FakeHttp::new()
+        .post("/events", 200, r#"{"success":true}"#)
+        .start()
+        .await;
+
+    let client = ClientBuilder::new().build().unwrap();
+    let api = api(&broker, "");
+    let password = password();
+    let credentials = ("guest", &password);
+    let host = broker.host();
+    let hosts = [host.as_str(), host.as_str()];
+
+    let federation: Vec<Vec<Info>> = fetch(&client, &api, credentials, &hosts, federify).await;
+    let mut events: Vec<Event> = federation.iter().filter_map(|info| compute(info)).collect();
+
+    let shovels: Vec<Vec<Shovel>> = fetch(&client, &api, credentials, &hosts, shovelify).await;
+    events.extend(shovel_events(shovels));
+
+    // one federation event per host and one event per unique shovel
+    assert_eq!(events.len(), 4);
+
+    let payload = Payload {
+        source: String::from("rmq"),
+        events,
+    };
+    let reply = send(&client, juggler.url(), &payload).await.unwrap();
+    let reply: Reply = serde_json::from_value(reply).unwrap();
+    assert!(reply.success);
+
+    let requests = juggler.requests();
+    assert_eq!(requests.len(), 1);
+
+    let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+    let events = body["events"].as_array().unwrap();
+
+    assert_eq!(events[0]["service"], "federation");
+    assert_eq!(events[0]["host"], "rmq1.example.com-test");
+    assert_eq!(events[0]["status"], "WARN");
+    assert_eq!(events[2]["service"], "shovel");
+    assert_eq!(events[2]["instance"], "orders");
+    assert_eq!(events[2]["status"], "OK");
+    assert_eq!(events[3]["instance"], "billing");
+    assert_eq!(events[3]["status"], "CRIT");
+    assert!(events[3]["description"]
+        .as_str()
+        .unwrap()
+        .ends_with("last error: econnrefused"));
+}
+
+#[tokio::test]
+async fn shovels_should_be_sent_without_federation_links() {
+    let broker = FakeHttp::new()
+        .get("/api/federation-links", 200, "[]")
+        .get("/api/shovels", 200, SHOVELS)
+        .start()
+        .await;
+
+    let client = ClientBuilder::new().build().unwrap();
+    let api = api(&broker, "");
+    let password = password();
+    let credentials = ("guest", &password);
+    let host = broker.host();
+    let hosts = [host.as_str()];
+
+    let federation: Vec<Vec<Info>> = fetch(&client, &api, credentials, &hosts, federify).await;
+    let mut events: Vec<Event> = federation.iter().filter_map(|info| compute(info)).collect();
+    assert!(events.is_empty());
+
+    let shovels: Vec<Vec<Shovel>> = fetch(&client, &api, credentials, &hosts, shovelify).await;
+    events.extend(shovel_events(shovels));
+
+    assert_eq!(events.len(), 2);
+    assert!(events.iter().all(|e| e.service == "shovel"));
+}
+
+#[tokio::test]
+async fn path_prefix_should_be_used() {
+    let broker = FakeHttp::new()
+        .get("/rabbitmq/api/shovels", 200, SHOVELS)
+        .start()
+        .await;
+
+    let client = ClientBuilder::new().build().unwrap();
+    let api = api(&broker, "/rabbitmq");
+    let password = password();
+    let host = broker.host();
+
+    let shovels: Vec<Vec<Shovel>> =
+        fetch(&client, &api, ("guest", &password), &[host.as_str()], shovelify).await;
+
+    assert_eq!(shovels.len(), 1);
+    assert_eq!(broker.requests()[0].path, "/rabbitmq/api/shovels");
+}
+
+#[tokio::test]
+async fn wrong_credentials_should_drop_host() {
+    let broker = FakeHttp::new()
+        .basic_auth("monitoring", "secret")
+        .get("/api/shovels", 200, SHOVELS)
+        .start()
+        .await;
+
+    let client = ClientBuilder::new().build().unwrap();
+    let api = api(&broker, "");
+    let password = password();
+    let host = broker.host();
+
+    let shovels: Vec<Vec<Shovel>> =
+        fetch(&client, &api, ("guest", &password), &[host.as_str()], shovelify).await;
+
+    assert!(shovels.is_empty());
+}
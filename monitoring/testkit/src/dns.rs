@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+
+use tokio::net::UdpSocket;
+use tokio::task::JoinHandle;
+
+const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+const TYPE_SRV: u16 = 33;
+const CLASS_IN: u16 = 1;
+
+#[derive(Clone, Debug, Default)]
+struct Records {
+    ips: HashMap<String, Vec<IpAddr>>,
+    // (target, port)
+    srv: HashMap<String, Vec<(String, u16)>>,
+}
+
+fn normalize(name: &str) -> String {
+    name.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// A DNS server over UDP answering A, AAAA and SRV queries from a record table.
+/// Answers have a zero TTL, so resolvers see record changes right away.
+pub struct FakeDns {
+    records: Records,
+}
+
+impl FakeDns {
+    pub fn new() -> Self {
+        FakeDns {
+            records: Records::default(),
+        }
+    }
+
+    /// A and AAAA records of a name.
+    pub fn ips(mut self, name: &str, ips: &[&str]) -> Self {
+        self.records.ips.insert(
+            normalize(name),
+            ips.iter().map(|ip| ip.parse().unwrap()).collect(),
+        );
+        self
+    }
+
+    /// SRV records of a name as `(target, port)`.
+    pub fn srv(mut self, name: &str, targets: &[(&str, u16)]) -> Self {
+        self.records.srv.insert(
+            normalize(name),
+            targets.iter().map(|(t, p)| (t.to_string(), *p)).collect(),
+        );
+        self
+    }
+
+    /// Binds to a random local port and answers queries until dropped.
+    pub async fn start(self) -> RunningDns {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+
+        let records = Arc::new(Mutex::new(self.records));
+        let served = records.clone();
+        let handle = tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            loop {
+                let (n, peer) = match socket.recv_from(&mut buf).await {
+                    Ok(received) => received,
+                    Err(_) => return,
+                };
+
+                let reply = answer(&buf[..n], &served.lock().unwrap().clone());
+                if let Some(reply) = reply {
+                    socket.send_to(&reply, peer).await.ok();
+                }
+            }
+        });
+
+        RunningDns {
+            addr,
+            records,
+            handle,
+        }
+    }
+}
+
+impl Default for FakeDns {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn encode_name(name: &str, out: &mut Vec<u8>) {
+    for label in name.trim_end_matches('.').split('.') {
+        out.push(label.len() as u8);
+        out.extend_from_slice(label.as_bytes());
+    }
+    out.push(0);
+}
+
+/// Builds the reply to a query, `None` for anything that is not a single question.
+fn answer(query: &[u8], records: &Records) -> Option<Vec<u8>> {
+    if query.len() < 12 || query[4..6] != [0, 1] {
+        return None;
+    }
+
+    // question name, its labels end with a zero length
+    let mut pos = 12;
+    let mut labels = Vec::new();
+    loop {
+        let len = *query.get(pos)? as usize;
+        pos += 1;
+        if len == 0 {
+            break;
+        }
+        labels.push(String::from_utf8_lossy(query.get(pos..pos + len)?).to_string());
+        pos += len;
+    }
+    let qtype = u16::from_be_bytes([*query.get(pos)?, *query.get(pos + 1)?]);
+    let question_end = pos + 4;
+    let question = query.get(12..question_end)?;
+    let name = normalize(&labels.join("."));
+
+    let mut rdatas: Vec<Vec<u8>> = Vec::new();
+    let known = records.ips.contains_key(&name) || records.srv.contains_key(&name);
+    match qtype {
+        TYPE_A | TYPE_AAAA => {
+            for ip in records.ips.get(&name).into_iter().flatten() {
+                match (qtype, ip) {
+                    (TYPE_A, IpAddr::V4(ip)) => rdatas.push(ip.octets().to_vec()),
+                    (TYPE_AAAA, IpAddr::V6(ip)) => rdatas.push(ip.octets().to_vec()),
+                    _ => {}
+                }
+            }
+        }
+        TYPE_SRV => {
+            for (target, port) in records.srv.get(&name).into_iter().flatten() {
+                // priority and weight
+                let mut rdata = vec![0, 0, 0, 0];
+                rdata.extend_from_slice(&port.to_be_bytes());
+                encode_name(target, &mut rdata);
+                rdatas.push(rdata);
+            }
+        }
+        _ => {}
+    }
+
+    let mut reply = Vec::with_capacity(512);
+    reply.extend_from_slice(&query[..2]);
+    // response, recursion desired and available, NXDOMAIN for unknown names
+    reply.extend_from_slice(&[0x81, if known { 0x80 } else { 0x83 }]);
+    reply.extend_from_slice(&[0, 1]);
+    reply.extend_from_slice(&(rdatas.len() as u16).to_be_bytes());
+    reply.extend_from_slice(&[0, 0, 0, 0]);
+    reply.extend_from_slice(question);
+
+    for rdata in rdatas {
+        // pointer to the question name
+        reply.extend_from_slice(&[0xc0, 12]);
+        reply.extend_from_slice(&qtype.to_be_bytes());
+        reply.extend_from_slice(&CLASS_IN.to_be_bytes());
+        reply.extend_from_slice(&0u32.to_be_bytes());
+        reply.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        reply.extend_from_slice(&rdata);
+    }
+
+    Some(reply)
+}
+
+pub struct RunningDns {
+    addr: SocketAddr,
+    records: Arc<Mutex<Records>>,
+    handle: JoinHandle<()>,
+}
+
+impl RunningDns {
+    /// `ip:port` of the server, for the resolver setting.
+    pub fn addr(&self) -> String {
+        self.addr.to_string()
+    }
+
+    /// Replaces the A and AAAA records of a name.
+    pub fn set_ips(&self, name: &str, ips: &[&str]) {
+        self.records.lock().unwrap().ips.insert(
+            normalize(name),
+            ips.iter().map(|ip| ip.parse().unwrap()).collect(),
+        );
+    }
+}
+
+impl Drop for RunningDns {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
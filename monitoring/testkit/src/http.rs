@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// A request received by the fake server.
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+#[derive(Clone)]
+struct Route {
+    status: u16,
+    body: String,
+}
+
+/// A minimal HTTP/1.1 server standing in for the RabbitMQ management
+/// and Juggler APIs. Every connection serves one request and is closed.
+pub struct FakeHttp {
+    routes: HashMap<(String, String), Route>,
+    auth: Option<String>,
+}
+
+impl FakeHttp {
+    pub fn new() -> Self {
+        FakeHttp {
+            routes: HashMap::new(),
+            auth: None,
+        }
+    }
+
+    pub fn get(self, path: &str, status: u16, body: &str) -> Self {
+        self.route("GET", path, status, body)
+    }
+
+    pub fn post(self, path: &str, status: u16, body: &str) -> Self {
+        self.route("POST", path, status, body)
+    }
+
+    pub fn route(mut self, method: &str, path: &str, status: u16, body: &str) -> Self {
+        self.routes.insert(
+            (method.to_string(), path.to_string()),
+            Route {
+                status,
+                body: body.to_string(),
+            },
+        );
+        self
+    }
+
+    /// Answers 401 to requests without these basic auth credentials.
+    pub fn basic_auth(mut self, login: &str, password: &str) -> Self {
+        self.auth = Some(format!("Basic {}", base64(format!("{login}:{password}").as_bytes())));
+        self
+    }
+
+    /// Binds to a random local port and serves requests until dropped.
+    pub async fn start(self) -> RunningHttp {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let routes = Arc::new(self.routes);
+        let auth = Arc::new(self.auth);
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let recorded = requests.clone();
+        let handle = tokio::spawn(async move {
+            loop {
+                let (conn, _) = match listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(_) => return,
+                };
+
+                let routes = routes.clone();
+                let auth = auth.clone();
+                let recorded = recorded.clone();
+
+                tokio::spawn(async move {
+                    serve(conn, &routes, auth.as_deref(), &recorded).await;
+                });
+            }
+        });
+
+        RunningHttp {
+            addr,
+            requests,
+            handle,
+        }
+    }
+}
+
+impl Default for FakeHttp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+async fn serve(
+    mut conn: TcpStream,
+    routes: &HashMap<(String, String), Route>,
+    auth: Option<&str>,
+    recorded: &Mutex<Vec<Request>>,
+) {
+    let request = match read_request(&mut conn).await {
+        Some(request) => request,
+        None => return,
+    };
+
+    let route_path = request.path.split('?').next().unwrap_or_default().to_string();
+    let authorized = auth.is_none_or(|expected| {
+        request.headers.get("authorization").map(String::as_str) == Some(expected)
+    });
+
+    let route = if !authorized {
+        Route {
+            status: 401,
+            body: String::from(r#"{"error":"not_authorised"}"#),
+        }
+    } else {
+        match routes.get(&(request.method.clone(), route_path)) {
+            Some(route) => route.clone(),
+            None => Route {
+                status: 404,
+                body: String::from(r#"{"error":"not_found"}"#),
+            },
+        }
+    };
+
+    recorded.lock().unwrap().push(request);
+
+    let response = format!(
+        "HTTP/1.1 {} Fake\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        route.status,
+        route.body.len(),
+        route.body
+    );
+    conn.write_all(response.as_bytes()).await.ok();
+    conn.shutdown().await.ok();
+}
+
+async fn read_request(conn: &mut TcpStream) -> Option<Request> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+
+    let head_end = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+        let n = conn.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+
+    let head = String::from_utf8_lossy(&buf[..head_end]).to_string();
+    let mut lines = head.lines();
+
+    let mut request_line = lines.next()?.split(' ');
+    let method = request_line.next()?.to_string();
+    let path = request_line.next()?.to_string();
+
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(k, v)| (k.trim().to_lowercase(), v.trim().to_string()))
+        .collect();
+
+    let length = headers
+        .get("content-length")
+        .and_then(|l| l.parse::<usize>().ok())
+        .unwrap_or(0);
+
+    let mut body = buf[head_end + 4..].to_vec();
+    while body.len() < length {
+        let n = conn.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+
+    Some(Request {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&body).to_string(),
+    })
+}
+
+fn base64(input: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::new();
+    for chunk in input.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+pub struct RunningHttp {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<Request>>>,
+    handle: JoinHandle<()>,
+}
+
+impl RunningHttp {
+    /// `http://host:port` without a trailing slash.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn host(&self) -> String {
+        self.addr.ip().to_string()
+    }
+
+    pub fn port(&self) -> u16 {
+        self.addr.port()
+    }
+
+    /// Requests received so far, oldest first.
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Drop for RunningHttp {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_should_pad() {
+        assert_eq!(base64(b"guest:guest"), "Z3Vlc3Q6Z3Vlc3Q=");
+        assert_eq!(base64(b"ab"), "YWI=");
+        assert_eq!(base64(b"abc"), "YWJj");
+    }
+}
@@ -0,0 +1,12 @@
+//! In-process fake servers for the monitoring integration tests.
+
+pub mod dns;
+pub mod http;
+pub mod redis;
+pub mod transcripts;
+pub mod zoo;
+
+pub use dns::{FakeDns, RunningDns};
+pub use http::{FakeHttp, Request, RunningHttp};
+pub use redis::{FakeRedis, RunningRedis};
+pub use zoo::{FakeZoo, Mode, RunningZoo};
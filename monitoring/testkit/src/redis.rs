@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// Encodes a bulk string reply.
+pub fn bulk(value: &str) -> String {
+    format!("${}\r\n{}\r\n", value.len(), value)
+}
+
+/// Encodes an array reply out of already encoded items.
+pub fn array(items: &[String]) -> String {
+    format!("*{}\r\n{}", items.len(), items.concat())
+}
+
+/// Encodes an integer reply.
+pub fn integer(value: i64) -> String {
+    format!(":{}\r\n", value)
+}
+
+/// A Redis stand-in answering RESP commands with canned replies.
+/// Unknown commands get an error reply, like a real server.
+pub struct FakeRedis {
+    replies: HashMap<String, String>,
+    password: Option<String>,
+}
+
+impl FakeRedis {
+    pub fn new() -> Self {
+        FakeRedis {
+            replies: HashMap::new(),
+            password: None,
+        }
+    }
+
+    /// Like `requirepass`: every command but `AUTH <password>` is refused until it succeeds.
+    pub fn password(mut self, password: &str) -> Self {
+        self.password = Some(password.to_string());
+        self
+    }
+
+    /// `reply` must be RESP encoded, see `bulk`, `array` and `integer`.
+    pub fn reply(mut self, cmd: &str, reply: &str) -> Self {
+        self.replies.insert(cmd.to_uppercase(), reply.to_string());
+        self
+    }
+
+    /// Binds to a random local port and serves connections until dropped.
+    pub async fn start(self) -> RunningRedis {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let replies = Arc::new(self.replies);
+        let password = Arc::new(self.password);
+
+        let handle = tokio::spawn(async move {
+            loop {
+                let (conn, _) = match listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(_) => return,
+                };
+
+                let (replies, password) = (replies.clone(), password.clone());
+                tokio::spawn(async move { serve(conn, &replies, password.as_deref()).await });
+            }
+        });
+
+        RunningRedis { addr, handle }
+    }
+}
+
+impl Default for FakeRedis {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+async fn serve(conn: TcpStream, replies: &HashMap<String, String>, password: Option<&str>) {
+    let mut conn = BufReader::new(conn);
+    let mut authenticated = password.is_none();
+
+    while let Some(args) = read_command(&mut conn).await {
+        let cmd = args.first().map(|c| c.to_uppercase()).unwrap_or_default();
+
+        let reply = if cmd == "AUTH" && password.is_some() {
+            // the password is the last argument, with or without an ACL user
+            authenticated = args.last().map(String::as_str) == password;
+            if authenticated {
+                String::from("+OK\r\n")
+            } else {
+                String::from("-WRONGPASS invalid username-password pair\r\n")
+            }
+        } else if !authenticated {
+            String::from("-NOAUTH Authentication required.\r\n")
+        } else {
+            match replies.get(&cmd) {
+                Some(reply) => reply.clone(),
+                None => format!("-ERR unknown command '{}'\r\n", cmd),
+            }
+        };
+
+        if conn.get_mut().write_all(reply.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+async fn read_command(conn: &mut BufReader<TcpStream>) -> Option<Vec<String>> {
+    let mut line = String::new();
+    if conn.read_line(&mut line).await.ok()? == 0 {
+        return None;
+    }
+
+    let count: usize = line.trim_end().strip_prefix('*')?.parse().ok()?;
+    let mut args = Vec::with_capacity(count);
+
+    for _ in 0..count {
+        line.clear();
+        conn.read_line(&mut line).await.ok()?;
+        let len: usize = line.trim_end().strip_prefix('$')?.parse().ok()?;
+
+        let mut arg = vec![0u8; len + 2];
+        conn.read_exact(&mut arg).await.ok()?;
+        arg.truncate(len);
+        args.push(String::from_utf8(arg).ok()?);
+    }
+
+    Some(args)
+}
+
+pub struct RunningRedis {
+    addr: SocketAddr,
+    handle: JoinHandle<()>,
+}
+
+impl RunningRedis {
+    /// `host:port` to hand to the collector.
+    pub fn addr(&self) -> String {
+        self.addr.to_string()
+    }
+}
+
+impl Drop for RunningRedis {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
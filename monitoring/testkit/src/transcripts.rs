@@ -0,0 +1,88 @@
+//! Canned 4lw replies captured from a 3-node ensemble.
+
+pub const MNTR_LEADER: &str = "zk_version\t3.8.1-74db005175a4ec545697012f9069cb9dcc8cdda7, built on 2023-01-25 16:31 UTC
+zk_server_state\tleader
+zk_znode_count\t5
+zk_watch_count\t0
+zk_ephemerals_count\t0
+zk_approximate_data_size\t182
+zk_open_file_descriptor_count\t66
+zk_max_file_descriptor_count\t1048576
+zk_followers\t2
+zk_synced_followers\t2
+zk_pending_syncs\t0
+zk_last_proposal_size\t-1
+zk_max_proposal_size\t-1
+zk_min_proposal_size\t-1
+";
+
+pub const MNTR_LEADER_DEGRADED: &str = "zk_version\t3.8.1-74db005175a4ec545697012f9069cb9dcc8cdda7, built on 2023-01-25 16:31 UTC
+zk_server_state\tleader
+zk_znode_count\t5
+zk_watch_count\t0
+zk_followers\t1
+zk_synced_followers\t1
+zk_pending_syncs\t0
+";
+
+pub const MNTR_FOLLOWER: &str = "zk_version\t3.8.1-74db005175a4ec545697012f9069cb9dcc8cdda7, built on 2023-01-25 16:31 UTC
+zk_server_state\tfollower
+zk_znode_count\t5
+zk_watch_count\t0
+zk_ephemerals_count\t0
+zk_approximate_data_size\t182
+zk_open_file_descriptor_count\t64
+zk_max_file_descriptor_count\t1048576
+";
+
+pub const CONF: &str = "clientPort=2181
+secureClientPort=-1
+dataDir=/data/version-2
+dataDirSize=67108880
+dataLogDir=/datalog/version-2
+dataLogSize=134217744
+tickTime=2000
+maxClientCnxns=60
+minSessionTimeout=4000
+maxSessionTimeout=40000
+clientPortListenBacklog=-1
+serverId=1
+initLimit=5
+syncLimit=2
+electionAlg=3
+electionPort=3888
+quorumPort=2888
+peerType=0
+membership:
+server.1=zoo1:2888:3888:participant;0.0.0.0:2181
+server.2=zoo2:2888:3888:participant;0.0.0.0:2181
+server.3=zoo3:2888:3888:participant;0.0.0.0:2181
+version=0
+";
+
+/// `cons` of a member serving a well behaved client, a noisy one and the connection asking.
+pub const CONS: &str = " /10.0.0.5:41234[1](queued=0,recved=120,sent=120,sid=0x100000bd5c50000,lop=PING,est=1682591234567,to=30000,lcxid=0x1f,lzxid=0x1a,lresp=1682591234570,llat=0,minlat=0,avglat=0,maxlat=3)
+ /10.0.0.9:50010[1](queued=0,recved=10,sent=10,sid=0x100000bd5c50001,lop=GETD,est=1682591234600,to=30000,lcxid=0x3,lzxid=0x1a,lresp=1682591234610,llat=1,minlat=0,avglat=1,maxlat=2)
+ /10.0.0.9:50011[1](queued=0,recved=10,sent=10,sid=0x100000bd5c50002,lop=GETD,est=1682591234600,to=30000,lcxid=0x3,lzxid=0x1a,lresp=1682591234610,llat=1,minlat=0,avglat=1,maxlat=2)
+ /10.0.0.9:50012[1](queued=0,recved=10,sent=10,sid=0x100000bd5c50003,lop=GETD,est=1682591234600,to=30000,lcxid=0x3,lzxid=0x1a,lresp=1682591234610,llat=1,minlat=0,avglat=1,maxlat=2)
+ /[fd00:0:0:0:0:0:0:7]:33000[1](queued=0,recved=4,sent=4,sid=0x100000bd5c50004,lop=PING,est=1682591234700,to=30000,lcxid=0x1,lzxid=0x1a,lresp=1682591234710,llat=0,minlat=0,avglat=0,maxlat=0)
+ /127.0.0.1:54016[0](queued=0,recved=1,sent=0)
+
+";
+
+pub const WCHS: &str = "4 connections watching 3 paths
+Total watches:7
+";
+
+/// `wchc` matching `CONS`: the noisy client holds most of the watches.
+pub const WCHC: &str = "0x100000bd5c50000
+\t/app/config
+0x100000bd5c50001
+\t/app/config
+\t/app/lock
+0x100000bd5c50002
+\t/app/lock
+\t/app/queue
+0x100000bd5c50003
+\t/app/queue
+";
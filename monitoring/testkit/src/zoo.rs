@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Duration};
+
+/// How the fake server answers a known command.
+#[derive(Clone, Debug)]
+pub enum Mode {
+    /// Reply with the whole transcript and close the connection.
+    Reply,
+    /// Wait before replying.
+    Slow(Duration),
+    /// Send only the first half of the transcript and close the connection.
+    Truncated,
+    /// Reply with garbage instead of the transcript.
+    Malformed,
+    /// Accept the command and never answer.
+    Silent,
+    /// Never answer the first connections, like `Silent`, then reply normally.
+    Flaky(usize),
+}
+
+/// A ZooKeeper four letter word responder replaying canned transcripts.
+pub struct FakeZoo {
+    transcripts: HashMap<String, String>,
+    mode: Mode,
+}
+
+impl FakeZoo {
+    pub fn new() -> Self {
+        FakeZoo {
+            transcripts: HashMap::new(),
+            mode: Mode::Reply,
+        }
+    }
+
+    pub fn transcript(mut self, cmd: &str, reply: &str) -> Self {
+        self.transcripts.insert(cmd.to_string(), reply.to_string());
+        self
+    }
+
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Binds to a random local port and serves connections until dropped.
+    pub async fn start(self) -> RunningZoo {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let transcripts = Arc::new(self.transcripts);
+        let mode = self.mode;
+        let accepted = Arc::new(AtomicUsize::new(0));
+
+        let handle = tokio::spawn(async move {
+            loop {
+                let (mut conn, _) = match listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(_) => return,
+                };
+
+                let transcripts = transcripts.clone();
+                let mode = mode.clone();
+                let nth = accepted.fetch_add(1, Ordering::SeqCst);
+
+                tokio::spawn(async move {
+                    let mut cmd = [0u8; 4];
+                    if conn.read_exact(&mut cmd).await.is_err() {
+                        return;
+                    }
+
+                    // real servers close the connection on unknown commands
+                    let reply = match transcripts.get(String::from_utf8_lossy(&cmd).as_ref()) {
+                        Some(reply) => reply.clone(),
+                        None => return,
+                    };
+
+                    let reply = match mode {
+                        Mode::Reply => reply,
+                        Mode::Slow(delay) => {
+                            sleep(delay).await;
+                            reply
+                        }
+                        Mode::Truncated => reply[..reply.len() / 2].to_string(),
+                        Mode::Malformed => String::from("\u{0}\u{1}garbage without tabs\n"),
+                        Mode::Silent => {
+                            sleep(Duration::from_secs(3600)).await;
+                            return;
+                        }
+                        Mode::Flaky(silent) if nth < silent => {
+                            sleep(Duration::from_secs(3600)).await;
+                            return;
+                        }
+                        Mode::Flaky(_) => reply,
+                    };
+
+                    conn.write_all(reply.as_bytes()).await.ok();
+                    conn.shutdown().await.ok();
+                });
+            }
+        });
+
+        RunningZoo { addr, handle }
+    }
+}
+
+impl Default for FakeZoo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct RunningZoo {
+    addr: SocketAddr,
+    handle: JoinHandle<()>,
+}
+
+impl RunningZoo {
+    /// `host:port` to hand to `collect`.
+    pub fn addr(&self) -> String {
+        self.addr.to_string()
+    }
+}
+
+impl Drop for RunningZoo {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
@@ -1,3 +1,6 @@
+//! Learning material, see the `zoo` section of the README: `anyhow` context adds the host to errors.
+//! Not maintained as a monitor, the agent's `zoo` check is.
+
 use anyhow::{Context, Result};
 use reqwest::{header::CONTENT_TYPE, ClientBuilder};
 use serde::Deserialize;
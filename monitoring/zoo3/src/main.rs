@@ -1,3 +1,6 @@
+//! Learning material, see the `zoo` section of the README: a `String` error wrapper with the host added by hand.
+//! Not maintained as a monitor, the agent's `zoo` check is.
+
 use reqwest::{header::CONTENT_TYPE, ClientBuilder};
 use serde::Deserialize;
 use std::process;
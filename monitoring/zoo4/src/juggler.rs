@@ -1,6 +1,7 @@
+use reqwest::{header::CONTENT_TYPE, Client};
 use serde::Serialize;
 
-#[allow(dead_code)]
+#[allow(dead_code, clippy::upper_case_acronyms)]
 #[derive(Serialize, Debug)]
 pub enum Status {
     OK,
@@ -27,3 +28,19 @@ pub struct Payload<'a> {
 pub fn jugglerify(fqdn: String) -> String {
     format!("{}/events", fqdn)
 }
+
+pub async fn send(
+    client: &Client,
+    fqdn: String,
+    payload: &Payload<'_>,
+) -> Result<serde_json::Value, reqwest::Error> {
+    client
+        .post(jugglerify(fqdn))
+        .header(CONTENT_TYPE, "application/json")
+        .json(payload)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+}
@@ -0,0 +1,2 @@
+pub mod juggler;
+pub mod zoo;
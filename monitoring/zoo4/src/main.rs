@@ -1,13 +1,13 @@
-use reqwest::{header::CONTENT_TYPE, ClientBuilder};
+//! Learning material, see the `zoo` section of the README: a `String` error wrapper with the host added by `map_err`.
+//! Not maintained as a monitor, the agent's `zoo` check is.
+
+use reqwest::ClientBuilder;
 use serde::Deserialize;
 use std::process;
 use tracing::{error, info, warn};
 
-mod juggler;
-use juggler::{jugglerify, Event, Payload};
-
-mod zoo;
-use zoo::{collect, compute, ZooError};
+use zoo::juggler::{send, Event, Payload};
+use zoo::zoo::{collect, compute, ZooError};
 
 const TIMEOUT: u64 = 3;
 
@@ -38,19 +38,19 @@ async fn main() {
 
     let events: Vec<Event> = responses
         .into_iter()
-        .filter(|r| match r {
-            Err(e) => {
-                warn!("{e}");
-                false
-            }
-            _ => true,
-        })
         .map(|r| {
-            let (host, data) = r.unwrap();
+            let (host, data) = r?;
             let hostname = host.split(".").next().unwrap();
 
             compute(hostname, data, expected_followers)
         })
+        .filter_map(|r| match r {
+            Err(e) => {
+                warn!("{e}");
+                None
+            }
+            Ok(event) => Some(event),
+        })
         .collect();
 
     if events.is_empty() {
@@ -68,24 +68,8 @@ async fn main() {
         .build()
         .unwrap();
 
-    let res = client
-        .post(jugglerify(config.juggler_url))
-        .header(CONTENT_TYPE, "application/json")
-        .json(&payload)
-        .send()
-        .await;
-
-    let response = match res {
-        Err(e) => {
-            error!("failed to reach Juggler: {:?}", e);
-            process::exit(1)
-        }
-        Ok(response) => response,
-    };
-
-    let reply: Result<serde_json::Value, reqwest::Error> = response.json().await;
-    if reply.is_err() {
-        error!("failed to parse Juggler response: {:?}", reply.err());
+    if let Err(e) = send(&client, config.juggler_url, &payload).await {
+        error!("failed to send events to Juggler: {:?}", e);
         process::exit(1)
     }
 
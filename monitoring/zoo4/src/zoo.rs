@@ -9,6 +9,7 @@ use tracing::debug;
 use crate::juggler::{Event, Status};
 
 const ZOO_CMD: &str = "mntr";
+const ZOO_PORT: u16 = 2181;
 const TIMEOUT: u64 = 2000;
 
 #[derive(Debug)]
@@ -20,12 +21,26 @@ impl std::fmt::Display for ZooError {
     }
 }
 
+// hosts may come with an explicit port, e.g. `127.0.0.1:21810` or `[fd00::7]:21810`,
+// a bare IPv6 address has more than one colon
+fn address(host: &str) -> String {
+    if host.starts_with('[') && host.ends_with(']') {
+        format!("{host}:{ZOO_PORT}")
+    } else if host.starts_with('[') || host.matches(':').count() == 1 {
+        host.to_string()
+    } else if host.contains(':') {
+        format!("[{host}]:{ZOO_PORT}")
+    } else {
+        format!("{host}:{ZOO_PORT}")
+    }
+}
+
 pub async fn collect(host: &str) -> Result<(&str, String), Box<dyn std::error::Error>> {
     debug!("connecting to {host}...");
 
     let mut conn = tokio::time::timeout(
         Duration::from_millis(TIMEOUT),
-        TcpStream::connect(format!("{host}:2181")),
+        TcpStream::connect(address(host)),
     )
     .await??;
 
@@ -42,13 +57,14 @@ pub async fn collect(host: &str) -> Result<(&str, String), Box<dyn std::error::E
     Ok((host, buf))
 }
 
-pub fn compute(host: &str, info: String, expected_followers: usize) -> Event {
+pub fn compute(host: &str, info: String, expected_followers: usize) -> Result<Event<'_>, ZooError> {
     let mut zoo_info = HashMap::new();
 
     for line in info.lines() {
-        let key_value: Vec<_> = line.split("\t").collect();
-        let k = key_value[0];
-        let v = key_value[1];
+        // truncated replies may end with a partial line
+        let Some((k, v)) = line.split_once('\t') else {
+            continue;
+        };
 
         if k == "zk_server_state" {
             zoo_info.insert("state", v);
@@ -63,11 +79,19 @@ pub fn compute(host: &str, info: String, expected_followers: usize) -> Event {
     let description;
     let status;
 
-    if zoo_info.get("state").unwrap() == &"follower" {
+    let state = zoo_info
+        .get("state")
+        .ok_or_else(|| ZooError(format!("{host}: zk_server_state is missing")))?;
+
+    if state == &"follower" {
         description = String::from("follower");
         status = Status::OK;
     } else {
-        let followers = zoo_info.get("followers").unwrap().parse::<usize>().unwrap();
+        let followers = zoo_info
+            .get("followers")
+            .ok_or_else(|| ZooError(format!("{host}: zk_synced_followers is missing")))?
+            .parse::<usize>()
+            .map_err(|err| ZooError(format!("{host}: zk_synced_followers: {err}")))?;
 
         description = format!("leader. followers: {followers}/{expected_followers}");
 
@@ -78,12 +102,12 @@ pub fn compute(host: &str, info: String, expected_followers: usize) -> Event {
         };
     };
 
-    Event {
+    Ok(Event {
         host: format!("{host}-test"),
         service: "state",
         instance: "",
         status,
         description,
         tags: vec!["zoo", "k8s", "monitoring"],
-    }
+    })
 }
@@ -0,0 +1,143 @@
+use reqwest::ClientBuilder;
+use tokio::time::Duration;
+
+use testkit::{transcripts, FakeHttp, FakeZoo, Mode};
+use zoo::juggler::{send, Payload, Status};
+use zoo::zoo::{collect, compute};
+
+#[tokio::test]
+async fn healthy_ensemble_should_be_sent_to_juggler() {
+    let leader = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_LEADER)
+        .start()
+        .await;
+    let follower1 = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_FOLLOWER)
+        .start()
+        .await;
+    let follower2 = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_FOLLOWER)
+        .start()
+        .await;
+    let juggler = FakeHttp::new()
+        .post("/events", 200, r#"{"accepted_events":3,"success":true}"#)
+        .start()
+        .await;
+
+    let hosts = [leader.addr(), follower1.addr(), follower2.addr()];
+
+    let mut events = Vec::new();
+    for host in &hosts {
+        let (host, data) = collect(host).await.unwrap();
+        events.push(compute(host, data, hosts.len() - 1).unwrap());
+    }
+
+    assert!(matches!(events[0].status, Status::OK));
+    assert_eq!(events[0].description, "leader. followers: 2/2");
+    assert_eq!(events[1].description, "follower");
+
+    let payload = Payload {
+        source: String::from("zoo"),
+        events,
+    };
+
+    let client = ClientBuilder::new().build().unwrap();
+    let reply = send(&client, juggler.url(), &payload).await.unwrap();
+    assert_eq!(reply["accepted_events"], 3);
+
+    let requests = juggler.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].path, "/events");
+    assert_eq!(requests[0].headers["content-type"], "application/json");
+
+    let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+    assert_eq!(body["source"], "zoo");
+    assert_eq!(body["events"].as_array().unwrap().len(), 3);
+    assert_eq!(body["events"][0]["status"], "OK");
+    assert_eq!(body["events"][0]["service"], "state");
+}
+
+#[tokio::test]
+async fn missing_followers_should_warn() {
+    let leader = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_LEADER_DEGRADED)
+        .start()
+        .await;
+
+    let addr = leader.addr();
+    let (host, data) = collect(&addr).await.unwrap();
+    let event = compute(host, data, 2).unwrap();
+
+    assert!(matches!(event.status, Status::WARN));
+    assert_eq!(event.description, "leader. followers: 1/2");
+}
+
+#[tokio::test]
+async fn slow_member_should_fail_collect() {
+    let member = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_FOLLOWER)
+        .mode(Mode::Slow(Duration::from_millis(500)))
+        .start()
+        .await;
+
+    assert!(collect(&member.addr()).await.is_err());
+}
+
+#[tokio::test]
+async fn silent_member_should_fail_collect() {
+    let member = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_FOLLOWER)
+        .mode(Mode::Silent)
+        .start()
+        .await;
+
+    assert!(collect(&member.addr()).await.is_err());
+}
+
+#[tokio::test]
+async fn truncated_reply_should_fail_compute() {
+    let leader = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_LEADER)
+        .mode(Mode::Truncated)
+        .start()
+        .await;
+
+    let addr = leader.addr();
+    let (host, data) = collect(&addr).await.unwrap();
+    let err = compute(host, data, 2).unwrap_err();
+
+    assert!(err.0.ends_with("zk_synced_followers is missing"));
+}
+
+#[tokio::test]
+async fn malformed_reply_should_fail_compute() {
+    let member = FakeZoo::new()
+        .transcript("mntr", transcripts::MNTR_FOLLOWER)
+        .mode(Mode::Malformed)
+        .start()
+        .await;
+
+    let addr = member.addr();
+    let (host, data) = collect(&addr).await.unwrap();
+    let err = compute(host, data, 2).unwrap_err();
+
+    assert!(err.0.ends_with("zk_server_state is missing"));
+}
+
+#[tokio::test]
+async fn juggler_errors_should_be_reported() {
+    let juggler = FakeHttp::new()
+        .post("/events", 500, r#"{"success":false}"#)
+        .start()
+        .await;
+
+    let payload = Payload {
+        source: String::from("zoo"),
+        events: vec![],
+    };
+
+    let client = ClientBuilder::new().build().unwrap();
+    let err = send(&client, juggler.url(), &payload).await.unwrap_err();
+
+    assert_eq!(err.status().map(|s| s.as_u16()), Some(500));
+}
@@ -1,3 +1,6 @@
+//! Learning material, see the `zoo` section of the README: enum errors derived with `thiserror`.
+//! Not maintained as a monitor, the agent's `zoo` check is.
+
 use reqwest::{header::CONTENT_TYPE, ClientBuilder};
 use serde::Deserialize;
 use std::process;